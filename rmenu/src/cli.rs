@@ -1,12 +1,19 @@
-use std::fs;
+use std::{fs, path::PathBuf, time::Duration};
 
 use anyhow::{bail, Context, Error};
 use clap::Parser;
 use egui::{Color32, FontData, FontDefinitions};
 
 use font_kit::{handle::Handle, source::SystemSource};
+use rmenu_history::DEFAULT_HALF_LIFE;
 
+use crate::fields::{parse_field_range, FieldRange};
+use crate::geometry::Length;
 use crate::item_filter::ItemFilters;
+use crate::keymap::{parse_accept_key, parse_binding, AcceptKey, Binding, KeymapPreset};
+use crate::output::OutputFormat;
+use crate::screen::MonitorSelection;
+use crate::theme::{parse_theme, Theme};
 
 const fn hex_from_ascii_byte(b: u8) -> Result<u8, u8> {
     match b {
@@ -17,7 +24,7 @@ const fn hex_from_ascii_byte(b: u8) -> Result<u8, u8> {
     }
 }
 
-fn parse_color(src: &str) -> Result<Color32, Error> {
+pub fn parse_color(src: &str) -> Result<Color32, Error> {
     let mut four_bit_channels = match src.as_bytes() {
         &[b'#', r, g, b] | &[r, g, b] => [r, r, g, g, b, b, b'f', b'f'],
         &[b'#', r, g, b, a] | &[r, g, b, a] => [r, r, g, g, b, b, a, a],
@@ -97,6 +104,62 @@ fn parse_font(src: &str) -> Result<FontDefinitions, Error> {
     Ok(definitions)
 }
 
+#[allow(clippy::unnecessary_wraps)]
+fn parse_monitor(src: &str) -> Result<MonitorSelection, Error> {
+    let selection = match src {
+        "focused" => MonitorSelection::Focused,
+        "pointer" => MonitorSelection::Pointer,
+        _ => match src.parse::<usize>() {
+            Ok(index) => MonitorSelection::Index(index),
+            Err(_) => MonitorSelection::Name(src.to_string()),
+        },
+    };
+
+    Ok(selection)
+}
+
+fn parse_length(src: &str) -> Result<Length, Error> {
+    let (length, value) = match src.strip_suffix('%') {
+        Some(percent) => {
+            let percent = percent
+                .parse()
+                .with_context(|| format!("failed to parse {src} as a percentage"))?;
+            (Length::Percent(percent), percent)
+        }
+        None => {
+            let pixels = src
+                .parse()
+                .with_context(|| format!("failed to parse {src} as a number of pixels"))?;
+            (Length::Pixels(pixels), pixels)
+        }
+    };
+
+    if !(value.is_finite() && value > 0.0) {
+        bail!("length should be greater than zero");
+    }
+
+    Ok(length)
+}
+
+fn parse_delimiter(src: &str) -> Result<u8, Error> {
+    let delimiter = match src {
+        "\\0" => b'\0',
+        "\\n" => b'\n',
+        "\\t" => b'\t',
+        _ => match src.as_bytes() {
+            &[byte] => byte,
+            _ => bail!("delimiter should be a single byte like `;`, `\\t` or `\\0`"),
+        },
+    };
+
+    Ok(delimiter)
+}
+
+#[allow(clippy::unnecessary_wraps)]
+fn parse_field_delimiter(src: &str) -> Result<String, Error> {
+    Ok(src.replace("\\t", "\t"))
+}
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
@@ -113,25 +176,88 @@ pub struct Cli {
     pub prompt: String,
 
     /// The normal background color in RGB format
-    #[arg(long, value_parser = parse_color, default_value = "3a3a3a")]
-    pub background_normal: Color32,
+    #[arg(long, value_parser = parse_color)]
+    pub background_normal: Option<Color32>,
 
     /// The normal foreground color in RGB format
-    #[arg(long, value_parser = parse_color, default_value = "d0d0d0")]
-    pub foreground_normal: Color32,
+    #[arg(long, value_parser = parse_color)]
+    pub foreground_normal: Option<Color32>,
 
     /// The selection background color in RGB format
-    #[arg(long, value_parser = parse_color, default_value = "85add4")]
-    pub background_selection: Color32,
+    #[arg(long, value_parser = parse_color)]
+    pub background_selection: Option<Color32>,
 
     /// The selection foreground color in RGB format
-    #[arg(long, value_parser = parse_color, default_value = "d0d0d0")]
-    pub foreground_selection: Color32,
+    #[arg(long, value_parser = parse_color)]
+    pub foreground_selection: Option<Color32>,
+
+    /// The foreground color of the characters matching the filter in RGB format
+    #[arg(long, value_parser = parse_color)]
+    pub foreground_match: Option<Color32>,
+
+    /// The foreground color of marked items in RGB format
+    #[arg(long, value_parser = parse_color)]
+    pub foreground_marked: Option<Color32>,
+
+    /// The foreground color of the prompt in RGB format
+    #[arg(long, value_parser = parse_color)]
+    pub foreground_prompt: Option<Color32>,
 
-    /// The height of the bar in pixels
+    /// The color of a border drawn around the bar in RGB format
+    #[arg(long, value_parser = parse_color)]
+    pub border_color: Option<Color32>,
+
+    /// The theme giving the colors not set by their own options, either the name of
+    /// a built-in theme (gruvbox, nord, solarized) or the path to a theme file.
+    /// When given on the command line the colors of the config file are not used
+    #[arg(long, value_parser = parse_theme)]
+    pub theme: Option<Theme>,
+
+    /// The height of the bar in pixels, or of each line when listing items on lines
     #[arg(long, default_value_t = 30.0)]
     pub height: f32,
 
+    /// The monitor to show the bar on, given as an index, an output name, `focused` or `pointer`.
+    /// Defaults to the main monitor
+    #[arg(long, value_parser = parse_monitor)]
+    pub monitor: Option<MonitorSelection>,
+
+    /// The number of lines to list items on below the input.
+    /// When zero the items are listed next to the input
+    #[arg(long, default_value_t = 0)]
+    pub lines: usize,
+
+    /// Allow marking several items with Tab and Shift+Tab, accepting all marked items at once
+    #[arg(long)]
+    pub multi: bool,
+
+    /// The width of the bar in pixels, or as a percentage of the monitor width like `50%`.
+    /// Defaults to the full width of the monitor
+    #[arg(long, value_parser = parse_length)]
+    pub width: Option<Length>,
+
+    /// The horizontal offset of the bar from the left edge of the monitor in pixels
+    #[arg(long)]
+    pub x: Option<f32>,
+
+    /// The vertical offset of the bar from the top edge of the monitor in pixels,
+    /// or from the bottom edge when placed at the bottom
+    #[arg(long)]
+    pub y: Option<f32>,
+
+    /// Place the bar at the bottom of the screen instead of the top
+    #[arg(long)]
+    pub bottom: bool,
+
+    /// Place the bar in the center of the screen instead of the top
+    #[arg(long, conflicts_with = "bottom")]
+    pub center: bool,
+
+    /// Show the bar as a layer-shell overlay, for Wayland compositors supporting wlr-layer-shell
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[arg(long)]
+    pub layer_shell: bool,
+
     /// The filter used to filter items against the search string
     #[arg(long, value_enum, default_value_t = ItemFilters::Substring)]
     pub item_filter: ItemFilters,
@@ -141,6 +267,106 @@ pub struct Cli {
     #[arg(long)]
     pub jql_filter: Option<String>,
 
+    /// The text the input starts with
+    #[arg(long, default_value = "")]
+    pub query: String,
+
+    /// Select the first item whose text or input line equals this on startup
+    #[arg(long)]
+    pub select: Option<String>,
+
+    /// Select the item read from this zero-based line of the input on startup
+    #[arg(long, conflicts_with = "select")]
+    pub select_index: Option<usize>,
+
+    /// The string separating the fields of plain text items, accepting `\t` as an escape.
+    /// Defaults to splitting on runs of whitespace
+    #[arg(long, value_parser = parse_field_delimiter)]
+    pub field_delimiter: Option<String>,
+
+    /// The fields of plain text items to show, like `1,3..` or `-1`.
+    /// The whole line is still printed when accepted
+    #[arg(long, value_parser = parse_field_range, value_delimiter = ',', conflicts_with = "jql_filter")]
+    pub with_nth: Vec<FieldRange>,
+
+    /// The fields of plain text items to match against the search string, like `with-nth`
+    #[arg(long, value_parser = parse_field_range, value_delimiter = ',', conflicts_with = "jql_filter")]
+    pub nth: Vec<FieldRange>,
+
+    /// A history file in the format of rmenu_history to rank items by, recording the
+    /// accepted items in it. Items are ranked and recorded by the text shown for them
+    #[arg(long)]
+    pub history: Option<PathBuf>,
+
+    /// How long it takes for the score of an item in the history to halve
+    #[arg(long, value_parser = humantime::parse_duration, default_value = DEFAULT_HALF_LIFE)]
+    pub history_half_life: Duration,
+
+    /// The preset of key bindings to start from
+    #[arg(long, value_enum, default_value_t = KeymapPreset::Default)]
+    pub keymap: KeymapPreset,
+
+    /// Bind a key to an action like `ctrl+j:next`, taking precedence over the preset.
+    /// Can be given several times or as a comma separated list, adding to the bindings of
+    /// the config file
+    #[arg(long, value_parser = parse_binding, value_delimiter = ',')]
+    pub bind: Vec<Binding>,
+
+    /// An additional key accepting the selection like `alt+1`, making rmenu exit with its own
+    /// exit code given like `alt+1:3`. Keys without one exit with 10, 11 and so on in the order
+    /// they are given, after those of the config file. Can be given several times or as a comma
+    /// separated list, adding to the accept keys of the config file
+    #[arg(long, value_parser = parse_accept_key, value_delimiter = ',')]
+    pub accept_key: Vec<AcceptKey>,
+
+    /// The byte separating items, both those read and those printed.
+    /// Accepts `\n`, `\t` and `\0` as escapes
+    #[arg(long, value_parser = parse_delimiter, default_value = "\\n")]
+    pub delimiter: u8,
+
+    /// Read items separated by NUL instead of the delimiter
+    #[arg(long)]
+    pub read0: bool,
+
+    /// Print items separated by NUL instead of the delimiter
+    #[arg(long)]
+    pub print0: bool,
+
+    /// What to print for each accepted item
+    #[arg(long, value_enum, default_value_t = OutputFormat::Value)]
+    pub output: OutputFormat,
+
+    /// Print the name of the key accepting the selection on the line before it,
+    /// or an empty line when accepted with the accept action
+    #[arg(long)]
+    pub print_accept_key: bool,
+
+    /// Print the text of the input on the line before the selection,
+    /// also when accepting without any matching item
+    #[arg(long)]
+    pub print_query: bool,
+
+    /// The profile of the config file to use, overriding its top level settings
+    #[arg(long)]
+    pub profile: Option<String>,
+
     #[arg(skip = 100.0)]
     pub input_min_width: f32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_length_rejects_lengths_which_are_not_positive() {
+        assert!(matches!(parse_length("200"), Ok(Length::Pixels(pixels)) if pixels == 200.0));
+        assert!(matches!(parse_length("50%"), Ok(Length::Percent(percent)) if percent == 50.0));
+
+        for src in ["0", "-5", "0%", "-10%", "inf", "NaN%"] {
+            assert!(parse_length(src).is_err(), "{src} should be rejected");
+        }
+
+        assert!(Cli::try_parse_from(["rmenu", "--width", "0"]).is_err());
+    }
+}
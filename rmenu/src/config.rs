@@ -0,0 +1,502 @@
+use std::{
+    env,
+    error::Error,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{
+    builder::ValueParser,
+    error::{ContextKind, ContextValue},
+    parser::ValueSource,
+    value_parser, Arg, ArgAction, ArgMatches, Command, CommandFactory, FromArgMatches,
+};
+use toml::{Table, Value};
+
+use crate::cli::Cli;
+use crate::theme::Theme;
+
+/// The key of the config file holding the named profiles
+const PROFILES_KEY: &str = "profiles";
+
+/// The arguments which cannot be set from the config file
+const IGNORED_ARGS: [&str; 3] = ["help", "version", "profile"];
+
+/// The key bindings, where those of the config file are kept when others are given
+/// on the command line
+const MERGED_ARGS: [&str; 2] = ["bind", "accept-key"];
+
+/// The path of the config file, `$XDG_CONFIG_HOME/rmenu/config.toml`
+fn config_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_home.join("rmenu").join("config.toml"))
+}
+
+fn read_config() -> Result<Option<(PathBuf, Table)>> {
+    let Some(path) = config_path().filter(|path| path.exists()) else {
+        return Ok(None);
+    };
+
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read config file {}", path.display()))?;
+    let config = contents
+        .parse::<Table>()
+        .with_context(|| format!("failed to parse config file {}", path.display()))?;
+
+    Ok(Some((path, config)))
+}
+
+/// The command line interface, where flags also accept a value like `--bottom=false`
+/// so flags enabled in the config file can be disabled on the command line
+fn command() -> Command {
+    Cli::command().mut_args(|arg| {
+        if !matches!(arg.get_action(), ArgAction::SetTrue) {
+            return arg;
+        }
+
+        arg.action(ArgAction::Set)
+            .value_parser(value_parser!(bool))
+            .num_args(0..=1)
+            .require_equals(true)
+            .default_value("false")
+            .default_missing_value("true")
+    })
+}
+
+/// The command line interface keeping the values as they are given, to find the arguments
+/// given on the command line without running the value parsers, which may read files
+fn unparsed_command() -> Command {
+    command().mut_args(|arg| arg.value_parser(ValueParser::os_string()))
+}
+
+/// Parses the command line, taking arguments not given on it from the config file.
+/// The keys of the config file are the long names of the arguments, and named
+/// profiles in `[profiles.NAME]` tables override the top level keys
+pub fn parse() -> Result<Cli> {
+    let args: Vec<OsString> = env::args_os().collect();
+    let config = read_config()?;
+
+    parse_from(
+        &args,
+        config
+            .as_ref()
+            .map(|(path, config)| (path.as_path(), config)),
+    )
+    .or_else(|err| match err.downcast::<clap::Error>() {
+        Ok(err) => err.exit(),
+        Err(err) => Err(err),
+    })
+}
+
+/// Parses the arguments together with those set by the config file, running the value
+/// parsers once. Errors in the arguments are returned as `clap::Error`
+fn parse_from(args: &[OsString], config: Option<(&Path, &Table)>) -> Result<Cli> {
+    // Falls back to the full parse to show its help, version or error
+    let matches = match unparsed_command().try_get_matches_from(args) {
+        Ok(matches) => matches,
+        Err(_) => command().try_get_matches_from(args)?,
+    };
+    let profile = matches
+        .get_raw("profile")
+        .and_then(|mut values| values.next())
+        .map(|profile| profile.to_string_lossy().into_owned());
+
+    let config_args = match config {
+        Some((path, config)) => config_args(config, profile.as_deref(), &matches)
+            .with_context(|| format!("invalid config file {}", path.display()))?,
+        None if profile.is_some() => bail!("failed to find a config file to read profiles from"),
+        None => Vec::new(),
+    };
+
+    let all_args = args
+        .iter()
+        .take(1)
+        .cloned()
+        .chain(config_args.iter().map(|config_arg| config_arg.arg.clone()))
+        .chain(args.iter().skip(1).cloned());
+
+    let matches = command().try_get_matches_from(all_args).map_err(|err| {
+        match (config, config_error(&err, &config_args)) {
+            (Some((path, _)), Some(config_err)) => {
+                config_err.context(format!("invalid config file {}", path.display()))
+            }
+            _ => err.into(),
+        }
+    })?;
+
+    Ok(Cli::from_arg_matches(&matches)?)
+}
+
+/// An argument set by the config file, with the key setting it
+struct ConfigArg {
+    key: String,
+    id: String,
+    /// The value given, `None` for flags
+    value: Option<String>,
+    arg: OsString,
+}
+
+/// The error pointing to the config key causing a parse error, `None` when the error
+/// is not caused by the config file
+fn config_error(err: &clap::Error, config_args: &[ConfigArg]) -> Option<anyhow::Error> {
+    let context = |kind: ContextKind| match err.get(kind) {
+        Some(ContextValue::String(value)) => Some(value.as_str()),
+        _ => None,
+    };
+
+    let invalid_arg = context(ContextKind::InvalidArg)?;
+    let invalid_value = context(ContextKind::InvalidValue);
+
+    // Arguments are only displayed like in errors once the command is built
+    let mut command = command();
+    command.build();
+    let arg = command
+        .get_arguments()
+        .find(|arg| arg.to_string() == invalid_arg)?;
+
+    let config_arg = config_args.iter().find(|config_arg| {
+        config_arg.id == arg.get_id().as_str()
+            && invalid_value.is_none_or(|invalid_value| {
+                config_arg
+                    .value
+                    .as_deref()
+                    .is_some_and(|value| match arg.get_value_delimiter() {
+                        Some(delimiter) => {
+                            value.split(delimiter).any(|value| value == invalid_value)
+                        }
+                        None => value == invalid_value,
+                    })
+            })
+    })?;
+
+    let reason = err
+        .source()
+        .map_or_else(|| err.kind().to_string(), ToString::to_string);
+    let key = &config_arg.key;
+
+    Some(match invalid_value {
+        Some(_) => anyhow!("invalid value for `{key}`: {reason}"),
+        None => anyhow!("invalid `{key}`: {reason}"),
+    })
+}
+
+/// Whether the argument conflicts with one given on the command line
+fn conflicts_with_command_line(command: &Command, arg: &Arg, matches: &ArgMatches) -> bool {
+    let is_conflict = |arg: &Arg, other: &Arg| {
+        command
+            .get_arg_conflicts_with(arg)
+            .iter()
+            .any(|conflict| conflict.get_id() == other.get_id())
+    };
+
+    command
+        .get_arguments()
+        .filter(|other| {
+            matches.value_source(other.get_id().as_str()) == Some(ValueSource::CommandLine)
+        })
+        .any(|other| is_conflict(arg, other) || is_conflict(other, arg))
+}
+
+/// The command line arguments set by the config file and profile, leaving out those
+/// already given on the command line or conflicting with arguments given on it.
+/// Key bindings are kept and given first, so those given on the command line take
+/// precedence. A theme given on the command line leaves out the colors of the config file
+fn config_args(
+    config: &Table,
+    profile: Option<&str>,
+    matches: &ArgMatches,
+) -> Result<Vec<ConfigArg>> {
+    let mut entries: Vec<(String, &Value)> = config
+        .iter()
+        .filter(|(key, _)| key.as_str() != PROFILES_KEY)
+        .map(|(key, value)| (key.clone(), value))
+        .collect();
+
+    if let Some(profile) = profile {
+        let profile_table = config
+            .get(PROFILES_KEY)
+            .and_then(|profiles| profiles.get(profile))
+            .with_context(|| format!("failed to find a profile named `{profile}`"))?
+            .as_table()
+            .with_context(|| format!("`{PROFILES_KEY}.{profile}` should be a table"))?;
+
+        for (key, value) in profile_table {
+            entries.retain(|(existing, _)| existing != key);
+            entries.push((format!("{PROFILES_KEY}.{profile}.{key}"), value));
+        }
+    }
+
+    let command = Cli::command();
+    let theme_on_command_line = matches.value_source("theme") == Some(ValueSource::CommandLine);
+    let mut args = Vec::new();
+
+    for (key, value) in entries {
+        let name = key.rsplit('.').next().unwrap_or(&key);
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(name) && !IGNORED_ARGS.contains(&name))
+            .ok_or_else(|| anyhow!("unknown key `{key}`"))?;
+
+        let is_list = matches!(arg.get_action(), ArgAction::Append);
+
+        if (!MERGED_ARGS.contains(&name)
+            && matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+            || conflicts_with_command_line(&command, arg, matches)
+            || (theme_on_command_line && Theme::is_color_key(name))
+        {
+            continue;
+        }
+
+        let flag = format!("--{name}");
+
+        if matches!(arg.get_action(), ArgAction::SetTrue) {
+            let enabled = value
+                .as_bool()
+                .ok_or_else(|| anyhow!("`{key}` should be true or false"))?;
+            if enabled {
+                args.push(ConfigArg {
+                    key: key.clone(),
+                    id: arg.get_id().to_string(),
+                    value: None,
+                    arg: OsString::from(flag),
+                });
+            }
+            continue;
+        }
+
+        let values = match value {
+            Value::Array(values) if is_list => values.iter().collect(),
+            _ => vec![value],
+        };
+
+        for value in values {
+            let value = match value {
+                Value::String(string) => string.clone(),
+                Value::Integer(_) | Value::Float(_) | Value::Boolean(_) => value.to_string(),
+                _ => bail!("`{key}` should be a string, a number or a boolean"),
+            };
+
+            args.push(ConfigArg {
+                key: key.clone(),
+                id: arg.get_id().to_string(),
+                // Passed as one argument so values starting with `-` are not read as arguments
+                arg: OsString::from(format!("{flag}={value}")),
+                value: Some(value),
+            });
+        }
+    }
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{Color32, Key, Modifiers};
+
+    use super::*;
+    use crate::keymap::{Action, Keymap};
+
+    fn args(config: &str, profile: Option<&str>, command_line: &[&str]) -> Result<Vec<String>> {
+        let config = config.parse::<Table>()?;
+        let matches =
+            unparsed_command().try_get_matches_from(["rmenu"].iter().chain(command_line))?;
+
+        let args = config_args(&config, profile, &matches)?;
+
+        Ok(args
+            .into_iter()
+            .map(|config_arg| config_arg.arg.to_string_lossy().into_owned())
+            .collect())
+    }
+
+    fn parse_with_config(config: &str, command_line: &[&str]) -> Result<Cli> {
+        let config = config.parse::<Table>()?;
+        let args: Vec<OsString> = ["rmenu"]
+            .iter()
+            .chain(command_line)
+            .map(OsString::from)
+            .collect();
+
+        parse_from(&args, Some((Path::new("config.toml"), &config)))
+    }
+
+    const CONFIG: &str = r#"
+        prompt = "run"
+        height = 24
+        bottom = true
+        center = false
+
+        [profiles.work]
+        prompt = "work"
+        lines = 10
+    "#;
+
+    #[test]
+    fn config_args_from_top_level_keys() -> Result<()> {
+        assert_eq!(
+            args(CONFIG, None, &[])?,
+            ["--bottom", "--height=24", "--prompt=run"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_args_from_profile_override_top_level_keys() -> Result<()> {
+        assert_eq!(
+            args(CONFIG, Some("work"), &[])?,
+            ["--bottom", "--height=24", "--lines=10", "--prompt=work"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_args_leave_out_command_line_arguments() -> Result<()> {
+        assert_eq!(
+            args(CONFIG, Some("work"), &["--prompt", "cli", "--bottom"])?,
+            ["--height=24", "--lines=10"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_args_leave_out_arguments_conflicting_with_the_command_line() -> Result<()> {
+        assert_eq!(
+            args(CONFIG, None, &["--center"])?,
+            ["--height=24", "--prompt=run"]
+        );
+        assert_eq!(
+            args("select = \"a\"\nlines = 3", None, &["--select-index", "1"])?,
+            ["--lines=3"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_args_leave_out_colors_for_a_theme_on_the_command_line() -> Result<()> {
+        let config = "theme = \"gruvbox\"\nforeground-normal = \"fff\"\nprompt = \"run\"";
+
+        assert_eq!(
+            args(config, None, &[])?,
+            ["--foreground-normal=fff", "--prompt=run", "--theme=gruvbox"]
+        );
+        assert_eq!(args(config, None, &["--theme", "nord"])?, ["--prompt=run"]);
+
+        let cli = parse_with_config(config, &["--theme", "nord", "--border-color", "000"])?;
+        assert_eq!(cli.foreground_normal, None);
+        assert_eq!(cli.border_color, Some(Color32::BLACK));
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_args_keep_bindings_given_on_the_command_line() -> Result<()> {
+        let config = "bind = [\"ctrl+j:next\", \"ctrl+k:prev\"]\naccept-key = \"alt+1\"";
+
+        assert_eq!(
+            args(config, None, &["--bind", "ctrl+j:first"])?,
+            [
+                "--accept-key=alt+1",
+                "--bind=ctrl+j:next",
+                "--bind=ctrl+k:prev"
+            ]
+        );
+
+        assert_eq!(
+            args("with-nth = \"1\"", None, &["--with-nth", "2"])?,
+            Vec::<String>::new()
+        );
+
+        let cli = parse_with_config(config, &["--bind", "ctrl+j:first"])?;
+        let keymap = Keymap::new(cli.keymap, &cli.bind, &cli.accept_key);
+        assert_eq!(keymap.action(Key::J, Modifiers::CTRL), Some(Action::First));
+        assert_eq!(keymap.action(Key::K, Modifiers::CTRL), Some(Action::Prev));
+        assert!(keymap.accept_key(Key::Num1, Modifiers::ALT).is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn flags_from_the_config_can_be_disabled() -> Result<()> {
+        let matches = command().try_get_matches_from(["rmenu", "--bottom", "--multi=false"])?;
+        let cli = Cli::from_arg_matches(&matches)?;
+        assert!(cli.bottom);
+        assert!(!cli.multi);
+
+        let matches = command().try_get_matches_from(["rmenu", "--bottom=false"])?;
+        let cli = Cli::from_arg_matches(&matches)?;
+        assert!(!cli.bottom);
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_args_accept_values_starting_with_a_dash() -> Result<()> {
+        let args = args("prompt = \"->\"\nx = -10", None, &[])?;
+        assert_eq!(args, ["--prompt=->", "--x=-10"]);
+
+        let cli = parse_with_config("prompt = \"->\"\nx = -10", &[])?;
+        assert_eq!(cli.prompt, "->");
+        assert_eq!(cli.x, Some(-10.0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_args_errors_point_to_the_key() {
+        let error = |config: &str, profile: Option<&str>| {
+            args(config, profile, &[])
+                .map_err(|err| err.to_string())
+                .unwrap_err()
+        };
+
+        assert_eq!(error("colour = \"fff\"", None), "unknown key `colour`");
+        assert_eq!(
+            error("", Some("home")),
+            "failed to find a profile named `home`"
+        );
+        assert_eq!(
+            error("[profiles.work]\nbottom = 1", Some("work")),
+            "`profiles.work.bottom` should be true or false"
+        );
+    }
+
+    #[test]
+    fn parse_errors_point_to_the_config_key() -> Result<()> {
+        let error = |config: &str, command_line: &[&str]| {
+            parse_with_config(config, command_line)
+                .map(|_| ())
+                .map_err(|err| format!("{err:#}"))
+                .unwrap_err()
+        };
+
+        assert!(error("foreground-normal = \"xyz\"", &[])
+            .starts_with("invalid config file config.toml: invalid value for `foreground-normal`"));
+        assert!(error("bind = \"ctrl+j:next,ctrl+k:nowhere\"", &[])
+            .starts_with("invalid config file config.toml: invalid value for `bind`"));
+        assert!(error("bottom = true\ncenter = true", &[])
+            .starts_with("invalid config file config.toml: invalid `"));
+
+        let err = parse_with_config("prompt = \"run\"", &["--height", "tall"])
+            .map(|_| ())
+            .unwrap_err();
+        assert!(err.downcast::<clap::Error>().is_ok());
+
+        let cli = parse_with_config(
+            "foreground-normal = \"xyz\"",
+            &["--foreground-normal", "fff"],
+        )?;
+        assert_eq!(cli.foreground_normal, Some(Color32::WHITE));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+use std::ops::Range;
+
+use anyhow::{anyhow, bail, Context, Error, Result};
+
+/// A range of fields given like fzf's field index expressions: `N`, `N..`, `..M` or `N..M`,
+/// counting from 1 or, for negative numbers, from the last field
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FieldRange {
+    start: Option<isize>,
+    end: Option<isize>,
+}
+
+impl FieldRange {
+    /// The indices of the fields in the range, for a line with `len` fields
+    fn indices(self, len: usize) -> Range<usize> {
+        #[allow(clippy::cast_possible_wrap)]
+        let len = len as isize;
+        let index = |field: isize| if field > 0 { field - 1 } else { len + field };
+
+        let start = self.start.map_or(0, index).max(0);
+        let end = self.end.map_or(len, |field| index(field) + 1).min(len);
+
+        #[allow(clippy::cast_sign_loss)]
+        if start < end {
+            start as usize..end as usize
+        } else {
+            0..0
+        }
+    }
+}
+
+fn parse_field(src: &str) -> Result<Option<isize>> {
+    if src.is_empty() {
+        return Ok(None);
+    }
+
+    match src.parse() {
+        Ok(0) => bail!("fields are counted from 1"),
+        Ok(field) => Ok(Some(field)),
+        Err(err) => Err(err).with_context(|| format!("failed to parse {src} as a field")),
+    }
+}
+
+pub fn parse_field_range(src: &str) -> Result<FieldRange, Error> {
+    let (start, end) = match src.split_once("..") {
+        Some((start, end)) => (parse_field(start)?, parse_field(end)?),
+        None => {
+            let field = parse_field(src)?.ok_or_else(|| anyhow!("field should not be empty"))?;
+            (Some(field), Some(field))
+        }
+    };
+
+    Ok(FieldRange { start, end })
+}
+
+/// A field of the searched text, and where the same field is shown in the key
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SearchField {
+    /// The char offset of the field in the searched text
+    pub offset: usize,
+    /// The length of the field in chars
+    pub len: usize,
+    /// The char offset of the field in the key, `None` when it is not shown
+    pub key_offset: Option<usize>,
+}
+
+/// How lines are split into fields, and which of them are shown and searched
+#[derive(Default)]
+pub struct Fields {
+    /// The string separating fields, or `None` to split on runs of whitespace
+    pub delimiter: Option<String>,
+    /// The fields shown, all of them if empty
+    pub with_nth: Vec<FieldRange>,
+    /// The fields searched, all of the shown text if empty
+    pub nth: Vec<FieldRange>,
+}
+
+impl Fields {
+    /// The fields of the line, with their char offsets in it
+    fn split<'a>(&self, line: &'a str) -> Vec<(usize, &'a str)> {
+        let fields: Vec<&str> = match &self.delimiter {
+            Some(delimiter) => line.split(delimiter.as_str()).collect(),
+            None => line.split_whitespace().collect(),
+        };
+
+        fields
+            .into_iter()
+            .map(|field| {
+                // The fields are slices of the line, so they start where their pointer is
+                let start = field.as_ptr() as usize - line.as_ptr() as usize;
+                (line[..start].chars().count(), field)
+            })
+            .collect()
+    }
+
+    /// The given fields joined by the delimiter, with the index and char offset of each
+    /// of them in the joined text, `None` when no ranges are given
+    fn project(
+        &self,
+        fields: &[(usize, &str)],
+        ranges: &[FieldRange],
+    ) -> Option<(String, Vec<(usize, usize)>)> {
+        if ranges.is_empty() {
+            return None;
+        }
+
+        let delimiter = self.delimiter.as_deref().unwrap_or(" ");
+        let mut text = String::new();
+        let mut offsets = Vec::new();
+        let mut offset = 0;
+
+        for index in ranges.iter().flat_map(|range| range.indices(fields.len())) {
+            if !offsets.is_empty() {
+                text.push_str(delimiter);
+                offset += delimiter.chars().count();
+            }
+
+            let field = fields[index].1;
+            text.push_str(field);
+            offsets.push((index, offset));
+            offset += field.chars().count();
+        }
+
+        Some((text, offsets))
+    }
+
+    /// The text shown for the line
+    pub fn key(&self, line: &str) -> String {
+        self.project(&self.split(line), &self.with_nth)
+            .map_or_else(|| line.to_string(), |(key, _)| key)
+    }
+
+    /// The text matched against the filter and where each of its fields is shown in
+    /// the key, `None` to match the shown text
+    pub fn search(&self, line: &str) -> Option<(String, Vec<SearchField>)> {
+        let fields = self.split(line);
+        let (search, offsets) = self.project(&fields, &self.nth)?;
+
+        // Without `--with-nth` the line is shown as it is
+        let key_offsets = self.project(&fields, &self.with_nth).map_or_else(
+            || {
+                fields
+                    .iter()
+                    .map(|(offset, _)| *offset)
+                    .enumerate()
+                    .collect()
+            },
+            |(_, key_offsets)| key_offsets,
+        );
+
+        let search_fields = offsets
+            .into_iter()
+            .map(|(index, offset)| SearchField {
+                offset,
+                len: fields[index].1.chars().count(),
+                key_offset: key_offsets
+                    .iter()
+                    .find(|(key_index, _)| *key_index == index)
+                    .map(|(_, key_offset)| *key_offset),
+            })
+            .collect();
+
+        Some((search, search_fields))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_field_range_reads_fzf_expressions() -> Result<()> {
+        let range = |src: &str, len: usize| parse_field_range(src).map(|range| range.indices(len));
+
+        assert_eq!(range("2", 3)?, 1..2);
+        assert_eq!(range("-1", 3)?, 2..3);
+        assert_eq!(range("2..", 3)?, 1..3);
+        assert_eq!(range("..-2", 3)?, 0..2);
+        assert_eq!(range("..", 3)?, 0..3);
+        assert_eq!(range("5", 3)?, 0..0);
+
+        assert!(parse_field_range("0").is_err());
+        assert!(parse_field_range("a..").is_err());
+        assert!(parse_field_range("").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn fields_choose_shown_and_searched_text() -> Result<()> {
+        let fields = Fields {
+            delimiter: Some("\t".to_string()),
+            with_nth: vec![parse_field_range("1")?, parse_field_range("3..")?],
+            nth: vec![parse_field_range("3")?],
+        };
+
+        assert_eq!(fields.key("name\t/path\ttag\tmore"), "name\ttag\tmore");
+        assert_eq!(
+            fields.search("name\t/path\ttag\tmore"),
+            Some((
+                "tag".to_string(),
+                vec![SearchField {
+                    offset: 0,
+                    len: 3,
+                    key_offset: Some(5),
+                }]
+            ))
+        );
+
+        let fields = Fields {
+            with_nth: vec![parse_field_range("2..")?],
+            ..Default::default()
+        };
+        assert_eq!(fields.key("  1  firefox   browser "), "firefox browser");
+        assert_eq!(fields.search("1 firefox"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn search_fields_point_to_the_shown_fields() -> Result<()> {
+        let fields = Fields {
+            delimiter: Some("\t".to_string()),
+            nth: vec![parse_field_range("2")?],
+            ..Default::default()
+        };
+        let search_fields = |line: &str| fields.search(line).map(|(_, fields)| fields);
+
+        assert_eq!(
+            search_fields("a\ta"),
+            Some(vec![SearchField {
+                offset: 0,
+                len: 1,
+                key_offset: Some(2),
+            }])
+        );
+
+        let fields = Fields {
+            nth: vec![parse_field_range("1")?, parse_field_range("3")?],
+            with_nth: vec![parse_field_range("3..")?],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            fields.search(" ab cd  éf gh"),
+            Some((
+                "ab éf".to_string(),
+                vec![
+                    SearchField {
+                        offset: 0,
+                        len: 2,
+                        key_offset: None,
+                    },
+                    SearchField {
+                        offset: 3,
+                        len: 2,
+                        key_offset: Some(0),
+                    },
+                ]
+            ))
+        );
+
+        Ok(())
+    }
+}
@@ -2,16 +2,19 @@ use anyhow::{Context, Result};
 use jql_runner::runner;
 use serde_json::Value;
 
+use crate::fields::Fields;
 use crate::item::Item;
 
 pub struct Filter {
     filter: Option<String>,
+    fields: Fields,
 }
 
 impl Filter {
-    pub fn new(filter: &Option<String>) -> Self {
+    pub fn new(filter: &Option<String>, fields: Fields) -> Self {
         Self {
             filter: filter.clone(),
+            fields,
         }
     }
 
@@ -21,17 +24,32 @@ impl Filter {
                 let input: Value =
                     serde_json::from_str(&item).context("failed to parse item as json")?;
 
-                let value = runner::raw(&filter, &input).context("failed to apply filter on item")?;
+                let value =
+                    runner::raw(filter, &input).context("failed to apply filter on item")?;
+
+                let key = value.to_string().trim_matches('"').to_string();
+
+                Ok(Item {
+                    search: key.clone(),
+                    search_fields: Vec::new(),
+                    key,
+                    value: item,
+                })
+            }
+            None => {
+                let key = self.fields.key(&item);
+                let (search, search_fields) = self
+                    .fields
+                    .search(&item)
+                    .unwrap_or_else(|| (key.clone(), Vec::new()));
 
                 Ok(Item {
-                    key: value.to_string().trim_matches('"').to_string(),
-                    value: item
+                    key,
+                    search,
+                    search_fields,
+                    value: item,
                 })
             }
-            None => Ok(Item {
-                key: item.clone(),
-                value: item,
-            }),
         }
     }
 }
@@ -0,0 +1,120 @@
+use egui::{Pos2, Rect, Vec2};
+
+/// A length given either in pixels or relative to the monitor
+#[derive(Clone, Copy)]
+pub enum Length {
+    Pixels(f32),
+    Percent(f32),
+}
+
+impl Length {
+    pub fn resolve(self, total: f32) -> f32 {
+        match self {
+            Length::Pixels(pixels) => pixels,
+            Length::Percent(percent) => total * percent / 100.0,
+        }
+    }
+}
+
+/// Where to place the bar on the monitor
+pub struct Geometry {
+    pub height: f32,
+    pub width: Option<Length>,
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+    pub bottom: bool,
+    pub center: bool,
+}
+
+impl Geometry {
+    /// The area covered by the bar, given the area covered by the monitor.
+    /// Offsets are relative to the top left corner of the monitor,
+    /// or the bottom edge for `y` when placed at the bottom
+    pub fn window_rect(&self, monitor: Rect) -> Rect {
+        let width = self
+            .width
+            .map_or(monitor.width(), |width| width.resolve(monitor.width()))
+            .min(monitor.width());
+        let height = self.height.min(monitor.height());
+
+        let x = match self.x {
+            Some(x) => monitor.min.x + x,
+            None if self.center => monitor.center().x - width / 2.0,
+            None => monitor.min.x,
+        };
+
+        let y = match self.y {
+            Some(y) if self.bottom => monitor.max.y - height - y,
+            Some(y) => monitor.min.y + y,
+            None if self.center => monitor.center().y - height / 2.0,
+            None if self.bottom => monitor.max.y - height,
+            None => monitor.min.y,
+        };
+
+        Rect::from_min_size(Pos2::new(x, y), Vec2::new(width, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor() -> Rect {
+        Rect::from_min_size(Pos2::new(1920.0, 0.0), Vec2::new(2000.0, 1000.0))
+    }
+
+    fn geometry() -> Geometry {
+        Geometry {
+            height: 30.0,
+            width: None,
+            x: None,
+            y: None,
+            bottom: false,
+            center: false,
+        }
+    }
+
+    #[test]
+    fn window_rect_defaults_to_top_full_width() {
+        let rect = geometry().window_rect(monitor());
+
+        assert_eq!(
+            rect,
+            Rect::from_min_size(Pos2::new(1920.0, 0.0), Vec2::new(2000.0, 30.0))
+        );
+    }
+
+    #[test]
+    fn window_rect_at_bottom_with_offsets() {
+        let geometry = Geometry {
+            width: Some(Length::Pixels(500.0)),
+            x: Some(100.0),
+            y: Some(10.0),
+            bottom: true,
+            ..geometry()
+        };
+
+        let rect = geometry.window_rect(monitor());
+
+        assert_eq!(
+            rect,
+            Rect::from_min_size(Pos2::new(2020.0, 960.0), Vec2::new(500.0, 30.0))
+        );
+    }
+
+    #[test]
+    fn window_rect_centered_with_percentage_width() {
+        let geometry = Geometry {
+            width: Some(Length::Percent(50.0)),
+            center: true,
+            ..geometry()
+        };
+
+        let rect = geometry.window_rect(monitor());
+
+        assert_eq!(
+            rect,
+            Rect::from_min_size(Pos2::new(2420.0, 485.0), Vec2::new(1000.0, 30.0))
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::{Context, Result};
+use rmenu_history::{HistoryItems, HistoryWarning};
+
+/// A history file in the format of `rmenu_history`, ranking items by how often and how
+/// recently they were accepted. Items are recorded by the text shown for them
+pub struct History {
+    path: PathBuf,
+    items: HistoryItems,
+    half_life: Duration,
+    now: u64,
+}
+
+impl History {
+    pub fn read(path: PathBuf, half_life: Duration) -> Result<Self> {
+        let now = rmenu_history::now()?;
+        let (items, warnings) = rmenu_history::parse_history_file(&path, now)
+            .with_context(|| format!("failed to read history file {}", path.display()))?;
+        report_warnings(warnings);
+
+        Ok(Self {
+            path,
+            items,
+            half_life,
+            now,
+        })
+    }
+
+    /// The score of the item, zero for items never accepted
+    pub fn frecency(&self, key: &str) -> f64 {
+        self.items
+            .get(key)
+            .map_or(0.0, |item| item.frecency(self.now, self.half_life))
+    }
+
+    /// Records the accepted items in the history file
+    pub fn record(&self, keys: Vec<String>) -> Result<()> {
+        let now = rmenu_history::now()?;
+
+        let ((), warnings) = rmenu_history::modify_history_file(&self.path, now, |history_items| {
+            for key in keys {
+                rmenu_history::select(history_items, key, now, self.half_life);
+            }
+
+            Ok(())
+        })
+        .with_context(|| format!("failed to update history file {}", self.path.display()))?;
+        report_warnings(warnings);
+
+        Ok(())
+    }
+}
+
+/// Reports the problems found in the history file on stderr, without failing
+fn report_warnings(warnings: Vec<HistoryWarning>) {
+    for warning in warnings {
+        eprintln!("{warning}");
+    }
+}
@@ -0,0 +1,162 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The editable text of the input together with a cursor.
+/// The cursor is a byte offset which is always on a grapheme boundary
+#[derive(Default)]
+pub struct Input {
+    text: String,
+    cursor: usize,
+}
+
+impl Input {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.text[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(offset, _)| offset)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.text[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |grapheme| self.cursor + grapheme.len())
+    }
+
+    /// The start of the word before the cursor, skipping whitespace in front of it
+    fn previous_word_boundary(&self) -> usize {
+        let before = self.text[..self.cursor].trim_end();
+
+        before
+            .grapheme_indices(true)
+            .rev()
+            .find(|(_, grapheme)| grapheme.chars().all(char::is_whitespace))
+            .map_or(0, |(offset, grapheme)| offset + grapheme.len())
+    }
+
+    pub fn insert(&mut self, text: &str) {
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.previous_boundary();
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = self.next_boundary();
+    }
+
+    pub fn move_to_start(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_to_end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    /// Deletes the grapheme before the cursor
+    pub fn delete_backward(&mut self) {
+        let start = self.previous_boundary();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Deletes the grapheme after the cursor
+    pub fn delete_forward(&mut self) {
+        let end = self.next_boundary();
+        self.text.replace_range(self.cursor..end, "");
+    }
+
+    /// Deletes the word before the cursor
+    pub fn delete_word(&mut self) {
+        let start = self.previous_word_boundary();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Deletes everything before the cursor
+    pub fn kill_line(&mut self) {
+        self.text.replace_range(..self.cursor, "");
+        self.cursor = 0;
+    }
+
+    /// Deletes everything after the cursor
+    pub fn kill_to_end(&mut self) {
+        self.text.truncate(self.cursor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(text: &str) -> Input {
+        let mut input = Input::default();
+        input.insert(text);
+        input
+    }
+
+    #[test]
+    fn edits_at_the_cursor() {
+        let mut input = input("fire");
+        input.move_left();
+        input.move_left();
+        input.insert("x");
+        assert_eq!(input.text(), "fixre");
+
+        input.delete_forward();
+        assert_eq!(input.text(), "fixe");
+
+        input.delete_backward();
+        input.delete_backward();
+        assert_eq!(input.text(), "fe");
+        assert_eq!(input.cursor(), 1);
+
+        input.move_to_end();
+        input.delete_forward();
+        assert_eq!(input.text(), "fe");
+        assert_eq!(input.cursor(), 2);
+    }
+
+    #[test]
+    fn deletes_whole_graphemes() {
+        let mut input = input("cafe\u{301}🇩🇰");
+        input.delete_backward();
+        assert_eq!(input.text(), "cafe\u{301}");
+
+        input.move_left();
+        assert_eq!(input.cursor(), 3);
+
+        input.delete_forward();
+        assert_eq!(input.text(), "caf");
+    }
+
+    #[test]
+    fn deletes_words_and_lines() {
+        let mut input = input("git  checkout main  ");
+        input.delete_word();
+        assert_eq!(input.text(), "git  checkout ");
+
+        input.delete_word();
+        assert_eq!(input.text(), "git  ");
+
+        input.move_left();
+        input.move_left();
+        input.kill_to_end();
+        assert_eq!(input.text(), "git");
+
+        input.move_left();
+        input.kill_line();
+        assert_eq!(input.text(), "t");
+        assert_eq!(input.cursor(), 0);
+    }
+}
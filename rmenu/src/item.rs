@@ -1,5 +1,38 @@
+use crate::fields::SearchField;
+
 #[derive(Clone)]
 pub struct Item {
+    /// The text shown for the item
     pub key: String,
+    /// The text matched against the filter, usually the same as the key
+    pub search: String,
+    /// Where the fields of the search text are shown in the key, empty when the search
+    /// text is the key
+    pub search_fields: Vec<SearchField>,
+    /// The line read from the input
     pub value: String,
 }
+
+impl Item {
+    /// Moves the positions of matched chars in the search text to the same chars in the key,
+    /// dropping those in fields which are not shown
+    pub fn key_positions(&self, positions: Vec<usize>) -> Vec<usize> {
+        if self.search_fields.is_empty() {
+            return positions;
+        }
+
+        positions
+            .into_iter()
+            .filter_map(|position| {
+                let field = self
+                    .search_fields
+                    .iter()
+                    .find(|field| (field.offset..field.offset + field.len).contains(&position))?;
+
+                field
+                    .key_offset
+                    .map(|key_offset| key_offset + position - field.offset)
+            })
+            .collect()
+    }
+}
@@ -6,6 +6,7 @@ use clap::ValueEnum;
 pub enum ItemFilters {
     Contains,
     Substring,
+    Fuzzy,
 }
 
 impl From<ItemFilters> for Arc<dyn ItemFilter> {
@@ -13,42 +14,224 @@ impl From<ItemFilters> for Arc<dyn ItemFilter> {
         match value {
             ItemFilters::Contains => Arc::new(ContainsFilter {}),
             ItemFilters::Substring => Arc::new(SubstringFilter {}),
+            ItemFilters::Fuzzy => Arc::new(FuzzyFilter {}),
         }
     }
 }
 
+/// How well an item matched a filter
+pub struct ItemMatch {
+    /// Higher is better, items with equal scores keep their input order
+    pub score: i64,
+    /// The indices of the matched chars in the item
+    pub positions: Vec<usize>,
+}
+
 pub trait ItemFilter {
-    fn filter(&self, filter: &str, item: &str) -> bool;
+    /// Matches the item against the filter, `None` if it does not match
+    fn filter(&self, filter: &str, item: &str) -> Option<ItemMatch>;
 }
 
 pub struct ContainsFilter {}
 impl ItemFilter for ContainsFilter {
-    fn filter(&self, filter: &str, item: &str) -> bool {
-        item.to_ascii_lowercase()
-            .contains(filter.to_ascii_lowercase().as_str())
+    fn filter(&self, filter: &str, item: &str) -> Option<ItemMatch> {
+        let start = item
+            .to_ascii_lowercase()
+            .find(filter.to_ascii_lowercase().as_str())?;
+        let start = item[..start].chars().count();
+
+        Some(ItemMatch {
+            score: 0,
+            positions: (start..start + filter.chars().count()).collect(),
+        })
     }
 }
 
 pub struct SubstringFilter {}
 impl ItemFilter for SubstringFilter {
-    fn filter(&self, filter: &str, item: &str) -> bool {
-        let mut item_chars = item.chars();
+    fn filter(&self, filter: &str, item: &str) -> Option<ItemMatch> {
+        let mut item_chars = item.chars().enumerate();
+        let mut positions = Vec::new();
 
         'outer: for a in filter.chars() {
             loop {
                 match item_chars.next() {
-                    Some(b) => {
-                        if a.to_ascii_lowercase() == b.to_ascii_lowercase() {
+                    Some((i, b)) => {
+                        if a.eq_ignore_ascii_case(&b) {
+                            positions.push(i);
                             continue 'outer;
                         }
                     }
                     None => {
-                        return false;
+                        return None;
                     }
                 }
             }
         }
 
-        true
+        Some(ItemMatch {
+            score: 0,
+            positions,
+        })
+    }
+}
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_PREFIX: i64 = 8;
+const BONUS_CONSECUTIVE: i64 = 8;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+
+fn chars_match(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+fn boundary_bonus(previous: Option<char>, current: char) -> i64 {
+    match previous {
+        None => BONUS_PREFIX + BONUS_BOUNDARY,
+        Some(previous) if !previous.is_alphanumeric() && current.is_alphanumeric() => {
+            BONUS_BOUNDARY
+        }
+        Some(previous) if previous.is_lowercase() && current.is_uppercase() => BONUS_BOUNDARY,
+        _ => 0,
+    }
+}
+
+/// A partial match score together with the position of the previously matched char
+type Cell = Option<(i64, usize)>;
+
+fn best(a: Cell, b: Cell) -> Cell {
+    match (a, b) {
+        (Some((score_a, _)), Some((score_b, _))) if score_b > score_a => b,
+        (None, _) => b,
+        _ => a,
+    }
+}
+
+/// Scores subsequence matches in the style of fzf/skim, rewarding consecutive
+/// runs and matches at the start of words while penalizing gaps
+pub struct FuzzyFilter {}
+impl ItemFilter for FuzzyFilter {
+    fn filter(&self, filter: &str, item: &str) -> Option<ItemMatch> {
+        let query: Vec<char> = filter.chars().collect();
+        let item: Vec<char> = item.chars().collect();
+
+        let Some((first, rest)) = query.split_first() else {
+            return Some(ItemMatch {
+                score: 0,
+                positions: Vec::new(),
+            });
+        };
+
+        let bonuses: Vec<i64> = item
+            .iter()
+            .enumerate()
+            .map(|(j, &c)| boundary_bonus(j.checked_sub(1).map(|k| item[k]), c))
+            .collect();
+
+        // rows[i][j] is the best score of matching query[..=i] with query[i] at j
+        let mut rows: Vec<Vec<Cell>> = vec![item
+            .iter()
+            .zip(&bonuses)
+            .enumerate()
+            .map(|(j, (&c, bonus))| chars_match(*first, c).then_some((SCORE_MATCH + bonus, j)))
+            .collect()];
+
+        for &q in rest {
+            let row = &rows[rows.len() - 1];
+            let mut next = vec![None; item.len()];
+            let mut gap_best: Cell = None;
+
+            for j in 1..item.len() {
+                if j >= 2 {
+                    let extended = gap_best.map(|(score, k)| (score - PENALTY_GAP_EXTENSION, k));
+                    let started = row[j - 2].map(|(score, _)| (score - PENALTY_GAP_START, j - 2));
+                    gap_best = best(extended, started);
+                }
+
+                if chars_match(q, item[j]) {
+                    let consecutive =
+                        row[j - 1].map(|(score, _)| (score + BONUS_CONSECUTIVE, j - 1));
+                    next[j] = best(consecutive, gap_best)
+                        .map(|(score, k)| (score + SCORE_MATCH + bonuses[j], k));
+                }
+            }
+
+            rows.push(next);
+        }
+
+        let (mut j, (score, _)) = rows[rows.len() - 1]
+            .iter()
+            .enumerate()
+            .filter_map(|(j, cell)| cell.map(|cell| (j, cell)))
+            .max_by_key(|(_, (score, _))| *score)?;
+
+        let mut positions = vec![0; rows.len()];
+        for (i, row) in rows.iter().enumerate().rev() {
+            positions[i] = j;
+            j = row[j].map_or(j, |(_, k)| k);
+        }
+
+        Some(ItemMatch { score, positions })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(filter: &str, item: &str) -> Option<i64> {
+        FuzzyFilter {}.filter(filter, item).map(|m| m.score)
+    }
+
+    fn positions(item_filter: &dyn ItemFilter, filter: &str, item: &str) -> Option<Vec<usize>> {
+        item_filter.filter(filter, item).map(|m| m.positions)
+    }
+
+    #[test]
+    fn fuzzy_requires_a_subsequence() {
+        assert!(score("fox", "firefox").is_some());
+        assert!(score("FOX", "firefox").is_some());
+        assert!(score("xof", "firefox").is_none());
+        assert_eq!(score("", "firefox"), Some(0));
+    }
+
+    #[test]
+    fn fuzzy_prefers_consecutive_matches() {
+        assert!(score("abc", "xabcx") > score("abc", "xaxbxcx"));
+        assert!(score("abc", "xaxbxcx") > score("abc", "xaxxbxxxcx"));
+    }
+
+    #[test]
+    fn fuzzy_prefers_word_boundaries_and_prefixes() {
+        assert!(score("code", "vs-code") > score("code", "xcodebuild"));
+        assert!(score("term", "terminal") > score("term", "xterm"));
+        assert!(score("gc", "GitCola") > score("gc", "magic"));
+    }
+
+    #[test]
+    fn filters_return_matched_positions() {
+        assert_eq!(
+            positions(&ContainsFilter {}, "FOX", "firefox"),
+            Some(vec![4, 5, 6])
+        );
+        assert_eq!(
+            positions(&ContainsFilter {}, "fox", "ﬁrefox"),
+            Some(vec![3, 4, 5])
+        );
+        assert_eq!(
+            positions(&SubstringFilter {}, "ffx", "firefox"),
+            Some(vec![0, 4, 6])
+        );
+        assert_eq!(
+            positions(&FuzzyFilter {}, "fox", "xfirefox"),
+            Some(vec![5, 6, 7])
+        );
+        assert_eq!(
+            positions(&FuzzyFilter {}, "gc", "git-cola"),
+            Some(vec![0, 4])
+        );
+        assert_eq!(positions(&FuzzyFilter {}, "xof", "firefox"), None);
     }
 }
@@ -1,67 +1,118 @@
 use egui::{
-    Color32, Label, Response, Stroke, Ui, Vec2, Widget, WidgetInfo, WidgetText, WidgetType,
+    text::{LayoutJob, TextFormat},
+    Color32, Label, Response, Stroke, Ui, Vec2, Widget, WidgetInfo, WidgetType,
 };
 
 pub trait ItemLabelExt {
-    fn item_label(
-        &mut self,
-        text: impl Into<WidgetText>,
-        foreground_color: Option<Color32>,
-        background_color: Option<Color32>,
-    ) -> Response;
+    fn item_label(&mut self, text: &str, matches: &[usize], colors: ItemColors) -> Response;
+
+    /// An item label whose background spans the full width of the row
+    fn item_row(&mut self, text: &str, matches: &[usize], colors: ItemColors) -> Response;
 }
 
 impl ItemLabelExt for Ui {
-    fn item_label(
-        &mut self,
-        text: impl Into<WidgetText>,
-        foreground_color: Option<Color32>,
-        background_color: Option<Color32>,
-    ) -> Response {
-        ItemLabel::new(text, foreground_color, background_color).ui(self)
+    fn item_label(&mut self, text: &str, matches: &[usize], colors: ItemColors) -> Response {
+        ItemLabel::new(text, matches, colors).ui(self)
+    }
+
+    fn item_row(&mut self, text: &str, matches: &[usize], colors: ItemColors) -> Response {
+        let mut label = ItemLabel::new(text, matches, colors);
+        label.full_width = true;
+        label.ui(self)
     }
 }
 
-struct ItemLabel {
-    text: WidgetText,
-    foreground_color: Option<Color32>,
-    background_color: Option<Color32>,
+/// The colors of an item label, falling back to the style for missing ones
+#[derive(Clone, Copy)]
+pub struct ItemColors {
+    pub foreground: Option<Color32>,
+    pub background: Option<Color32>,
+    pub matched: Color32,
 }
 
-impl ItemLabel {
-    fn new(
-        text: impl Into<WidgetText>,
-        foreground_color: Option<Color32>,
-        background_color: Option<Color32>,
-    ) -> Self {
+struct ItemLabel<'a> {
+    text: &'a str,
+    matches: &'a [usize],
+    colors: ItemColors,
+    full_width: bool,
+}
+
+impl<'a> ItemLabel<'a> {
+    fn new(text: &'a str, matches: &'a [usize], colors: ItemColors) -> Self {
         Self {
-            text: text.into(),
-            foreground_color,
-            background_color,
+            text,
+            matches,
+            colors,
+            full_width: false,
         }
     }
+
+    /// Lays out the text with the matched chars in the match color
+    fn layout_job(&self, ui: &Ui) -> LayoutJob {
+        let font_id = ui.style().override_font_id.clone().unwrap_or_default();
+        let color = self
+            .colors
+            .foreground
+            .unwrap_or_else(|| ui.visuals().text_color());
+
+        let mut job = LayoutJob::default();
+        for (text, matched) in split_matches(self.text, self.matches) {
+            let format = TextFormat {
+                font_id: font_id.clone(),
+                color: if matched { self.colors.matched } else { color },
+                ..Default::default()
+            };
+            job.append(text, 0.0, format);
+        }
+
+        job
+    }
 }
 
-impl Widget for ItemLabel {
-    fn ui(self, ui: &mut Ui) -> Response {
-        let text = match self.foreground_color {
-            Some(color) => self.text.color(color),
-            None => self.text,
-        };
+/// Splits the text into runs of chars that are all either matched or not
+fn split_matches<'a>(text: &'a str, matches: &[usize]) -> Vec<(&'a str, bool)> {
+    let mut runs = Vec::new();
+    let mut run_start = 0;
+    let mut run_matched = false;
+
+    for (index, (offset, _)) in text.char_indices().enumerate() {
+        let matched = matches.contains(&index);
+        if matched != run_matched {
+            if offset > run_start {
+                runs.push((&text[run_start..offset], run_matched));
+            }
+            run_start = offset;
+            run_matched = matched;
+        }
+    }
+
+    if run_start < text.len() {
+        runs.push((&text[run_start..], run_matched));
+    }
 
-        let label = Label::new(text);
+    runs
+}
+
+impl Widget for ItemLabel<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let label = Label::new(self.layout_job(ui));
 
         let (pos, text_galley, response) = label.layout_in_ui(ui);
 
         let painter = ui.painter();
 
-        if let Some(color) = self.background_color {
+        if let Some(color) = self.colors.background {
             let max = ui.max_rect();
 
             let mut rect = response.rect;
             rect.extend_with_y(max.min.y);
             rect.extend_with_y(max.max.y);
-            rect = rect.expand2(Vec2::new(5.0, 0.0));
+            if self.full_width {
+                rect.extend_with_x(max.min.x);
+                rect.extend_with_x(max.max.x);
+            } else {
+                rect = rect.expand2(Vec2::new(5.0, 0.0));
+            }
 
             painter.rect_filled(rect, 0.0, color);
         }
@@ -95,3 +146,22 @@ impl Widget for ItemLabel {
         response
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_matches_groups_runs() {
+        assert_eq!(
+            split_matches("firefox", &[0, 4, 5]),
+            vec![("f", true), ("ire", false), ("fo", true), ("x", false)]
+        );
+        assert_eq!(
+            split_matches("ﬁre", &[1]),
+            vec![("ﬁ", false), ("r", true), ("e", false)]
+        );
+        assert_eq!(split_matches("fox", &[]), vec![("fox", false)]);
+        assert_eq!(split_matches("", &[]), vec![]);
+    }
+}
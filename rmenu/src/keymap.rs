@@ -0,0 +1,458 @@
+use std::fmt;
+
+use anyhow::{anyhow, bail, Context, Error, Result};
+use clap::ValueEnum;
+use egui::{Key, Modifiers};
+
+/// The names of keys in key chords, the first name of a key is used when showing it
+const KEY_NAMES: [(&str, Key); 72] = [
+    ("up", Key::ArrowUp),
+    ("down", Key::ArrowDown),
+    ("left", Key::ArrowLeft),
+    ("right", Key::ArrowRight),
+    ("escape", Key::Escape),
+    ("esc", Key::Escape),
+    ("tab", Key::Tab),
+    ("backspace", Key::Backspace),
+    ("enter", Key::Enter),
+    ("return", Key::Enter),
+    ("space", Key::Space),
+    ("insert", Key::Insert),
+    ("delete", Key::Delete),
+    ("del", Key::Delete),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("pageup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+    ("minus", Key::Minus),
+    ("equals", Key::PlusEquals),
+    ("0", Key::Num0),
+    ("1", Key::Num1),
+    ("2", Key::Num2),
+    ("3", Key::Num3),
+    ("4", Key::Num4),
+    ("5", Key::Num5),
+    ("6", Key::Num6),
+    ("7", Key::Num7),
+    ("8", Key::Num8),
+    ("9", Key::Num9),
+    ("a", Key::A),
+    ("b", Key::B),
+    ("c", Key::C),
+    ("d", Key::D),
+    ("e", Key::E),
+    ("f", Key::F),
+    ("g", Key::G),
+    ("h", Key::H),
+    ("i", Key::I),
+    ("j", Key::J),
+    ("k", Key::K),
+    ("l", Key::L),
+    ("m", Key::M),
+    ("n", Key::N),
+    ("o", Key::O),
+    ("p", Key::P),
+    ("q", Key::Q),
+    ("r", Key::R),
+    ("s", Key::S),
+    ("t", Key::T),
+    ("u", Key::U),
+    ("v", Key::V),
+    ("w", Key::W),
+    ("x", Key::X),
+    ("y", Key::Y),
+    ("z", Key::Z),
+    ("f1", Key::F1),
+    ("f2", Key::F2),
+    ("f3", Key::F3),
+    ("f4", Key::F4),
+    ("f5", Key::F5),
+    ("f6", Key::F6),
+    ("f7", Key::F7),
+    ("f8", Key::F8),
+    ("f9", Key::F9),
+    ("f10", Key::F10),
+    ("f11", Key::F11),
+    ("f12", Key::F12),
+    ("f13", Key::F13),
+    ("f14", Key::F14),
+    ("f15", Key::F15),
+    ("f16", Key::F16),
+];
+
+/// The names of modifiers in key chords, in the order they are shown
+const MODIFIER_NAMES: [(&str, Modifiers); 4] = [
+    ("ctrl", Modifiers::CTRL),
+    ("alt", Modifiers::ALT),
+    ("shift", Modifiers::SHIFT),
+    ("cmd", Modifiers::MAC_CMD),
+];
+
+/// A key together with the modifiers held while pressing it, written like `ctrl+n`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyChord {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+impl KeyChord {
+    pub const fn new(key: Key, modifiers: Modifiers) -> Self {
+        Self { key, modifiers }
+    }
+
+    pub fn matches(&self, key: Key, modifiers: Modifiers) -> bool {
+        self.key == key && modifiers.matches(self.modifiers)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, modifier) in MODIFIER_NAMES {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{name}+")?;
+            }
+        }
+
+        let name = KEY_NAMES
+            .iter()
+            .find(|(_, key)| *key == self.key)
+            .map_or("?", |(name, _)| name);
+
+        write!(f, "{name}")
+    }
+}
+
+pub fn parse_key_chord(src: &str) -> Result<KeyChord, Error> {
+    let mut parts: Vec<&str> = src.split('+').collect();
+    let key_name = parts.pop().unwrap_or_default().to_ascii_lowercase();
+
+    let key = KEY_NAMES
+        .iter()
+        .find(|(name, _)| *name == key_name)
+        .map(|(_, key)| *key)
+        .with_context(|| format!("unknown key `{key_name}` in {src}"))?;
+
+    let mut modifiers = Modifiers::NONE;
+    for part in parts {
+        let part = part.to_ascii_lowercase();
+        let modifier = MODIFIER_NAMES
+            .iter()
+            .find(|(name, _)| *name == part)
+            .map(|(_, modifier)| *modifier)
+            .with_context(|| format!("unknown modifier `{part}` in {src}"))?;
+        modifiers = modifiers | modifier;
+    }
+
+    Ok(KeyChord::new(key, modifiers))
+}
+
+/// The actions keys can be bound to
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    /// Select the next item
+    Next,
+    /// Select the previous item
+    Prev,
+    /// Select the first item of the next page
+    PageDown,
+    /// Select the first item of the previous page
+    PageUp,
+    /// Select the first item
+    First,
+    /// Select the last item
+    Last,
+    /// Accept the selected or marked items
+    Accept,
+    /// Accept the text of the input as it is, whether it matches an item or not
+    AcceptQuery,
+    /// Close without accepting anything
+    Cancel,
+    /// Toggle the mark on the selected item and select the next one
+    ToggleMark,
+    /// Toggle the mark on the selected item and select the previous one
+    ToggleMarkPrev,
+    /// Select the previous item when listing items next to the input, else move the cursor left
+    Left,
+    /// Select the next item when listing items next to the input, else move the cursor right
+    Right,
+    /// Select the first item when listing items next to the input, else move the cursor
+    /// to the start
+    Home,
+    /// Select the last item when listing items next to the input, else move the cursor
+    /// to the end
+    End,
+    /// Move the cursor one character left
+    BackwardChar,
+    /// Move the cursor one character right
+    ForwardChar,
+    /// Move the cursor to the start of the input
+    BeginningOfLine,
+    /// Move the cursor to the end of the input
+    EndOfLine,
+    /// Delete the character before the cursor
+    BackwardDeleteChar,
+    /// Delete the character after the cursor
+    DeleteChar,
+    /// Delete the word before the cursor
+    BackwardKillWord,
+    /// Delete everything before the cursor
+    KillLine,
+    /// Delete everything after the cursor
+    KillToEnd,
+    /// Delete the whole input
+    ClearInput,
+}
+
+/// A key chord bound to an action, written like `ctrl+n:next`
+#[derive(Clone, Copy)]
+pub struct Binding {
+    pub chord: KeyChord,
+    pub action: Action,
+}
+
+pub fn parse_binding(src: &str) -> Result<Binding, Error> {
+    let Some((chord, action)) = src.rsplit_once(':') else {
+        bail!("binding {src} should be written as KEY:ACTION");
+    };
+
+    let chord = parse_key_chord(chord)?;
+    let action = Action::from_str(action, true)
+        .map_err(|_| anyhow!("unknown action `{action}` in {src}"))?;
+
+    Ok(Binding { chord, action })
+}
+
+/// The exit code of the first accept key given without one, the following count up from it
+const FIRST_ACCEPT_KEY_EXIT_CODE: i32 = 10;
+
+/// An additional key accepting the selection, making rmenu exit with its own exit code.
+/// Written like `alt+1` or `alt+1:3`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AcceptKey {
+    pub chord: KeyChord,
+    pub exit_code: Option<i32>,
+}
+
+pub fn parse_accept_key(src: &str) -> Result<AcceptKey, Error> {
+    let (chord, exit_code) = match src.rsplit_once(':') {
+        Some((chord, exit_code)) => {
+            let exit_code = exit_code
+                .parse()
+                .with_context(|| format!("failed to parse exit code `{exit_code}` in {src}"))?;
+            (chord, Some(exit_code))
+        }
+        None => (src, None),
+    };
+
+    Ok(AcceptKey {
+        chord: parse_key_chord(chord)?,
+        exit_code,
+    })
+}
+
+/// The sets of bindings keys can start from
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum KeymapPreset {
+    #[default]
+    Default,
+    Emacs,
+    Vi,
+}
+
+const fn bind(key: Key, modifiers: Modifiers, action: Action) -> Binding {
+    Binding {
+        chord: KeyChord::new(key, modifiers),
+        action,
+    }
+}
+
+const DEFAULT_BINDINGS: [Binding; 24] = [
+    bind(Key::Escape, Modifiers::NONE, Action::Cancel),
+    bind(Key::Enter, Modifiers::NONE, Action::Accept),
+    bind(Key::Enter, Modifiers::SHIFT, Action::AcceptQuery),
+    bind(Key::ArrowUp, Modifiers::NONE, Action::Prev),
+    bind(Key::ArrowDown, Modifiers::NONE, Action::Next),
+    bind(Key::ArrowLeft, Modifiers::NONE, Action::Left),
+    bind(Key::ArrowRight, Modifiers::NONE, Action::Right),
+    bind(Key::PageUp, Modifiers::NONE, Action::PageUp),
+    bind(Key::PageDown, Modifiers::NONE, Action::PageDown),
+    bind(Key::Home, Modifiers::NONE, Action::Home),
+    bind(Key::End, Modifiers::NONE, Action::End),
+    bind(Key::Tab, Modifiers::NONE, Action::ToggleMark),
+    bind(Key::Tab, Modifiers::SHIFT, Action::ToggleMarkPrev),
+    bind(Key::Backspace, Modifiers::NONE, Action::BackwardDeleteChar),
+    bind(Key::Delete, Modifiers::NONE, Action::DeleteChar),
+    bind(Key::ArrowLeft, Modifiers::CTRL, Action::BackwardChar),
+    bind(Key::ArrowRight, Modifiers::CTRL, Action::ForwardChar),
+    bind(Key::Home, Modifiers::CTRL, Action::BeginningOfLine),
+    bind(Key::End, Modifiers::CTRL, Action::EndOfLine),
+    bind(Key::A, Modifiers::CTRL, Action::BeginningOfLine),
+    bind(Key::E, Modifiers::CTRL, Action::EndOfLine),
+    bind(Key::W, Modifiers::CTRL, Action::BackwardKillWord),
+    bind(Key::U, Modifiers::CTRL, Action::KillLine),
+    bind(Key::K, Modifiers::CTRL, Action::KillToEnd),
+];
+
+const EMACS_BINDINGS: [Binding; 8] = [
+    bind(Key::N, Modifiers::CTRL, Action::Next),
+    bind(Key::P, Modifiers::CTRL, Action::Prev),
+    bind(Key::F, Modifiers::CTRL, Action::ForwardChar),
+    bind(Key::B, Modifiers::CTRL, Action::BackwardChar),
+    bind(Key::D, Modifiers::CTRL, Action::DeleteChar),
+    bind(Key::G, Modifiers::CTRL, Action::Cancel),
+    bind(Key::V, Modifiers::CTRL, Action::PageDown),
+    bind(Key::V, Modifiers::ALT, Action::PageUp),
+];
+
+const VI_BINDINGS: [Binding; 6] = [
+    bind(Key::J, Modifiers::CTRL, Action::Next),
+    bind(Key::K, Modifiers::CTRL, Action::Prev),
+    bind(Key::D, Modifiers::CTRL, Action::PageDown),
+    bind(Key::U, Modifiers::CTRL, Action::PageUp),
+    bind(Key::H, Modifiers::CTRL, Action::BackwardDeleteChar),
+    bind(Key::C, Modifiers::CTRL, Action::Cancel),
+];
+
+/// The key bindings of the selector, where later bindings take precedence over earlier ones
+pub struct Keymap {
+    bindings: Vec<Binding>,
+    accept_keys: Vec<AcceptKey>,
+}
+
+impl Keymap {
+    pub fn new(preset: KeymapPreset, bindings: &[Binding], accept_keys: &[AcceptKey]) -> Self {
+        let preset_bindings: &[Binding] = match preset {
+            KeymapPreset::Default => &[],
+            KeymapPreset::Emacs => &EMACS_BINDINGS,
+            KeymapPreset::Vi => &VI_BINDINGS,
+        };
+
+        let accept_keys = (FIRST_ACCEPT_KEY_EXIT_CODE..)
+            .zip(accept_keys)
+            .map(|(exit_code, accept_key)| AcceptKey {
+                exit_code: accept_key.exit_code.or(Some(exit_code)),
+                ..*accept_key
+            })
+            .collect();
+
+        Self {
+            bindings: [&DEFAULT_BINDINGS, preset_bindings, bindings].concat(),
+            accept_keys,
+        }
+    }
+
+    /// The accept key pressed, which takes precedence over the bindings
+    pub fn accept_key(&self, key: Key, modifiers: Modifiers) -> Option<AcceptKey> {
+        self.accept_keys
+            .iter()
+            .rev()
+            .find(|accept_key| accept_key.chord.matches(key, modifiers))
+            .copied()
+    }
+
+    pub fn action(&self, key: Key, modifiers: Modifiers) -> Option<Action> {
+        self.bindings
+            .iter()
+            .rev()
+            .find(|binding| binding.chord.matches(key, modifiers))
+            .map(|binding| binding.action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The modifiers egui reports for Ctrl outside macOS
+    const CTRL: Modifiers = Modifiers {
+        ctrl: true,
+        command: true,
+        ..Modifiers::NONE
+    };
+
+    #[test]
+    fn parse_key_chord_reads_modifiers_and_keys() -> Result<()> {
+        assert_eq!(
+            parse_key_chord("ctrl+n")?,
+            KeyChord::new(Key::N, Modifiers::CTRL)
+        );
+        assert_eq!(
+            parse_key_chord("Alt+Shift+Enter")?,
+            KeyChord::new(Key::Enter, Modifiers::ALT | Modifiers::SHIFT)
+        );
+        assert_eq!(
+            parse_key_chord("pagedown")?,
+            KeyChord::new(Key::PageDown, Modifiers::NONE)
+        );
+        assert_eq!(parse_key_chord("alt+return")?.to_string(), "alt+enter");
+
+        assert!(parse_key_chord("ctrl+").is_err());
+        assert!(parse_key_chord("hyper+n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_binding_reads_chord_and_action() -> Result<()> {
+        let binding = parse_binding("ctrl+j:page-down")?;
+        assert_eq!(binding.chord, KeyChord::new(Key::J, Modifiers::CTRL));
+        assert_eq!(binding.action, Action::PageDown);
+
+        assert!(parse_binding("ctrl+j").is_err());
+        assert!(parse_binding("ctrl+j:jump").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn keymap_prefers_later_bindings() -> Result<()> {
+        let keymap = Keymap::new(KeymapPreset::Default, &[], &[]);
+        assert_eq!(
+            keymap.action(Key::Enter, Modifiers::NONE),
+            Some(Action::Accept)
+        );
+        assert_eq!(
+            keymap.action(Key::Enter, Modifiers::SHIFT),
+            Some(Action::AcceptQuery)
+        );
+        assert_eq!(keymap.action(Key::K, CTRL), Some(Action::KillToEnd));
+        assert_eq!(keymap.action(Key::N, CTRL), None);
+
+        let keymap = Keymap::new(KeymapPreset::Vi, &[parse_binding("ctrl+n:next")?], &[]);
+        assert_eq!(keymap.action(Key::K, CTRL), Some(Action::Prev));
+        assert_eq!(keymap.action(Key::N, CTRL), Some(Action::Next));
+        assert_eq!(keymap.action(Key::N, Modifiers::NONE), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn accept_keys_count_up_exit_codes() -> Result<()> {
+        assert_eq!(
+            parse_accept_key("alt+1:3")?,
+            AcceptKey {
+                chord: KeyChord::new(Key::Num1, Modifiers::ALT),
+                exit_code: Some(3),
+            }
+        );
+        assert!(parse_accept_key("alt+1:three").is_err());
+
+        let accept_keys = ["alt+1", "alt+2:3", "shift+enter"]
+            .into_iter()
+            .map(parse_accept_key)
+            .collect::<Result<Vec<_>>>()?;
+        let keymap = Keymap::new(KeymapPreset::Default, &[], &accept_keys);
+
+        let exit_code = |key, modifiers| {
+            keymap
+                .accept_key(key, modifiers)
+                .and_then(|accept_key| accept_key.exit_code)
+        };
+        assert_eq!(exit_code(Key::Num1, Modifiers::ALT), Some(10));
+        assert_eq!(exit_code(Key::Num2, Modifiers::ALT), Some(3));
+        assert_eq!(exit_code(Key::Enter, Modifiers::SHIFT), Some(12));
+        assert_eq!(exit_code(Key::Enter, Modifiers::NONE), None);
+
+        Ok(())
+    }
+}
@@ -1,93 +1,248 @@
 use std::{
-    io::BufRead,
-    sync::mpsc::{self, Receiver, Sender},
+    io::{self, BufReader, Write},
+    process,
+    sync::{
+        mpsc::{self, Receiver},
+        Arc,
+    },
 };
 
 use anyhow::{anyhow, Context, Result};
-use clap::Parser;
 use cli::Cli;
-use cocoa::appkit::NSScreen;
-use cocoa::base::nil;
 
-use egui::{Pos2, Vec2};
+use fields::Fields;
 use filter::Filter;
-use item::Item;
-use selector::{AppColors, AppFont, Selector};
+use geometry::Geometry;
+use history::History;
+use keymap::Keymap;
+use output::OutputFormat;
+use selector::{Accepted, AppFont, AppLayout, AppOptions, Preselect, Selector};
+use theme::Theme;
 
 mod cli;
+mod config;
+mod fields;
 mod filter;
+mod geometry;
+mod history;
+mod input;
 mod item;
 mod item_filter;
 mod item_label;
+mod keymap;
+mod output;
+mod paging;
+mod reader;
+mod screen;
 mod selector;
+mod theme;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod wayland;
 
-fn get_main_screen_width() -> f32 {
-    let frame = unsafe {
-        let object = NSScreen::mainScreen(nil);
-        object.frame()
+fn main() -> Result<()> {
+    let cli: Cli = config::parse()?;
+    let fields = Fields {
+        delimiter: cli.field_delimiter,
+        with_nth: cli.with_nth,
+        nth: cli.nth,
     };
+    let filter = Filter::new(&cli.jql_filter, fields);
 
-    let size = frame.size;
+    let theme = Theme {
+        background_normal: cli.background_normal,
+        foreground_normal: cli.foreground_normal,
+        background_selection: cli.background_selection,
+        foreground_selection: cli.foreground_selection,
+        foreground_match: cli.foreground_match,
+        foreground_marked: cli.foreground_marked,
+        foreground_prompt: cli.foreground_prompt,
+        border_color: cli.border_color,
+    };
+    // The config file leaves out its colors when a theme is given on the command line, so
+    // colors come from the command line, its theme, the config file and then its theme
+    let app_colors = theme.or(cli.theme.unwrap_or_default()).app_colors();
 
-    size.width as f32
-}
+    let app_font = AppFont {
+        size: cli.font_size,
+        definitions: cli.font_definitions,
+    };
 
-fn main() -> Result<()> {
-    let cli: Cli = Cli::parse();
-    let filter = Filter::new(&cli.jql_filter);
+    let history = cli
+        .history
+        .map(|path| History::read(path, cli.history_half_life))
+        .transpose()?
+        .map(Arc::new);
+
+    let (tx, rx) = mpsc::channel();
+    let input_delimiter = if cli.read0 { b'\0' } else { cli.delimiter };
+    let output = Output {
+        format: cli.output,
+        delimiter: if cli.print0 { b'\0' } else { cli.delimiter },
+        print_query: cli.print_query,
+        print_accept_key: cli.print_accept_key,
+    };
+
+    let app_layout = AppLayout {
+        input_min_width: cli.input_min_width,
+        lines: cli.lines,
+        line_height: cli.height,
+    };
 
-    // TODO: should this be a im::Vector
-    let items: Vec<Item> = std::io::stdin()
-        .lock()
-        .lines()
-        .map(|result| result.map(|item| filter.to_item(item))?)
-        .collect::<Result<_>>()?;
+    let app_options = AppOptions {
+        multi: cli.multi,
+        keymap: Keymap::new(cli.keymap, &cli.bind, &cli.accept_key),
+        query: cli.query,
+        preselect: cli
+            .select
+            .map(Preselect::Value)
+            .or(cli.select_index.map(Preselect::Index)),
+        history: history.clone(),
+    };
+
+    #[allow(clippy::cast_precision_loss)]
+    let geometry = Geometry {
+        height: cli.height * (cli.lines + 1) as f32,
+        width: cli.width,
+        x: cli.x,
+        y: cli.y,
+        bottom: cli.bottom,
+        center: cli.center,
+    };
+
+    let app_creator = move |ctx: &egui::Context| {
+        let repaint_ctx = ctx.clone();
+        let items = reader::read_items(
+            BufReader::new(io::stdin()),
+            input_delimiter,
+            filter,
+            move || {
+                repaint_ctx.request_repaint();
+            },
+        );
+
+        Selector::new(
+            ctx,
+            items,
+            cli.item_filter.into(),
+            cli.prompt,
+            app_colors,
+            app_font,
+            app_layout,
+            app_options,
+            tx,
+        )
+    };
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    if cli.layer_shell {
+        let options = wayland::LayerShellOptions {
+            geometry,
+            monitor: cli.monitor,
+        };
 
-    let width = get_main_screen_width();
+        wayland::run_layer_shell(&options, app_creator)?;
+
+        return print_result(&rx, &output, history.as_deref());
+    }
+
+    let monitor = screen::find_monitor(cli.monitor.as_ref())?;
+
+    // eframe places and sizes windows in logical points
+    let rect = geometry.window_rect(monitor.logical_rect());
 
     let native_options = eframe::NativeOptions {
         decorated: false,
-        initial_window_size: Some(Vec2::new(width, cli.height)),
+        initial_window_size: Some(rect.size()),
         resizable: false,
         always_on_top: true,
-        initial_window_pos: Some(Pos2::new(0.0, 0.0)),
+        initial_window_pos: Some(rect.min),
         ..Default::default()
     };
 
-    let app_colors = AppColors {
-        foreground_normal: cli.foreground_normal,
-        foreground_selection: cli.foreground_selection,
-        background_normal: cli.background_normal,
-        background_selection: cli.background_selection,
-    };
-
-    let app_font = AppFont {
-        size: cli.font_size,
-        definitions: cli.font_definitions,
-    };
-
-    let (tx, rx): (Sender<Option<String>>, Receiver<Option<String>>) = mpsc::channel();
-
     eframe::run_native(
         "rmenu",
         native_options,
-        Box::new(|cc| {
-            Box::new(Selector::new(
-                cc,
-                items,
-                cli.item_filter.into(),
-                cli.prompt,
-                app_colors,
-                app_font,
-                tx,
-            ))
-        }),
+        Box::new(move |cc| Box::new(app_creator(&cc.egui_ctx))),
     )
     .map_err(|err| anyhow!("{err}"))?;
 
-    if let Some(item) = rx.try_recv().context("failed to get result")? {
-        println!("{item}");
+    print_result(&rx, &output, history.as_deref())
+}
+
+/// How to print the accepted items
+struct Output {
+    format: OutputFormat,
+    /// The byte written after each printed line
+    delimiter: u8,
+    print_query: bool,
+    print_accept_key: bool,
+}
+
+fn print_result(
+    rx: &Receiver<Result<Option<Accepted>>>,
+    output: &Output,
+    history: Option<&History>,
+) -> Result<()> {
+    let Some(accepted) = rx.try_recv().context("failed to get result")?? else {
+        return Ok(());
+    };
+
+    let mut lines = Vec::new();
+
+    if output.print_query {
+        lines.push(accepted.query.clone());
+    }
+
+    if output.print_accept_key {
+        let accept_key = accepted
+            .accept_key
+            .map(|accept_key| accept_key.chord.to_string());
+        lines.push(accept_key.unwrap_or_default());
+    }
+
+    for accepted_item in &accepted.items {
+        lines.push(output.format.format(accepted_item, &accepted));
+    }
+
+    let mut stdout = io::stdout().lock();
+    for line in lines {
+        stdout
+            .write_all(line.as_bytes())
+            .and_then(|()| stdout.write_all(&[output.delimiter]))
+            .context("failed to write result")?;
+    }
+    stdout.flush().context("failed to write result")?;
+
+    // Recorded after printing so a failing history never changes the result
+    if let Some(history) = history {
+        record_history(history, &accepted);
+    }
+
+    if let Some(exit_code) = accepted
+        .accept_key
+        .and_then(|accept_key| accept_key.exit_code)
+    {
+        process::exit(exit_code);
     }
 
     Ok(())
 }
+
+/// Records the accepted items from the input in the history, not an accepted query,
+/// reporting failures without failing
+fn record_history(history: &History, accepted: &Accepted) {
+    let keys: Vec<String> = accepted
+        .items
+        .iter()
+        .filter(|accepted_item| accepted_item.index.is_some())
+        .map(|accepted_item| accepted_item.item.key.clone())
+        .collect();
+
+    if keys.is_empty() {
+        return;
+    }
+
+    if let Err(err) = history.record(keys) {
+        eprintln!("Warning: {err:#}");
+    }
+}
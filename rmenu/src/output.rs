@@ -0,0 +1,112 @@
+use clap::ValueEnum;
+use serde_json::json;
+
+use crate::selector::{Accepted, AcceptedItem};
+
+/// What is printed for each accepted item
+#[derive(ValueEnum, Clone, Copy)]
+pub enum OutputFormat {
+    /// The line read from the input
+    Value,
+    /// The text shown for the item, as given by the jql filter
+    Key,
+    /// The zero-based line number of the item in the input
+    Index,
+    /// A JSON object with the index, key, value, query and accept key
+    Json,
+}
+
+impl OutputFormat {
+    /// Formats an accepted item as a line of output
+    pub fn format(self, accepted_item: &AcceptedItem, accepted: &Accepted) -> String {
+        match self {
+            Self::Value => accepted_item.item.value.clone(),
+            Self::Key => accepted_item.item.key.clone(),
+            Self::Index => accepted_item
+                .index
+                .map(|index| index.to_string())
+                .unwrap_or_default(),
+            Self::Json => json!({
+                "index": accepted_item.index,
+                "key": accepted_item.item.key,
+                "value": accepted_item.item.value,
+                "query": accepted.query,
+                "accept_key": accepted
+                    .accept_key
+                    .map(|accept_key| accept_key.chord.to_string()),
+            })
+            .to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use egui::{Key, Modifiers};
+    use serde_json::Value;
+
+    use super::*;
+    use crate::item::Item;
+    use crate::keymap::{AcceptKey, KeyChord};
+
+    fn accepted(index: Option<usize>, accept_key: Option<AcceptKey>) -> Accepted {
+        Accepted {
+            items: vec![AcceptedItem {
+                index,
+                item: Item {
+                    key: "firefox".to_string(),
+                    search: "firefox".to_string(),
+                    search_fields: Vec::new(),
+                    value: r#"{"name":"firefox"}"#.to_string(),
+                },
+            }],
+            query: "fire".to_string(),
+            accept_key,
+        }
+    }
+
+    #[test]
+    fn format_prints_the_chosen_field() {
+        let accepted = accepted(Some(3), None);
+        let item = &accepted.items[0];
+
+        assert_eq!(
+            OutputFormat::Value.format(item, &accepted),
+            r#"{"name":"firefox"}"#
+        );
+        assert_eq!(OutputFormat::Key.format(item, &accepted), "firefox");
+        assert_eq!(OutputFormat::Index.format(item, &accepted), "3");
+
+        let accepted = self::accepted(None, None);
+        assert_eq!(
+            OutputFormat::Index.format(&accepted.items[0], &accepted),
+            ""
+        );
+    }
+
+    #[test]
+    fn format_json_includes_query_and_accept_key() -> Result<()> {
+        let accept_key = AcceptKey {
+            chord: KeyChord::new(Key::Num1, Modifiers::ALT),
+            exit_code: Some(10),
+        };
+        let accepted = accepted(Some(0), Some(accept_key));
+
+        let json: Value =
+            serde_json::from_str(&OutputFormat::Json.format(&accepted.items[0], &accepted))?;
+
+        assert_eq!(
+            json,
+            json!({
+                "index": 0,
+                "key": "firefox",
+                "value": r#"{"name":"firefox"}"#,
+                "query": "fire",
+                "accept_key": "alt+1",
+            })
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use std::ops::Range;
+
+/// Splits items of the given widths into consecutive pages, each fitting in the available width.
+/// An item wider than the available width gets a page of its own
+pub fn paginate(widths: impl IntoIterator<Item = f32>, available: f32) -> Vec<Range<usize>> {
+    let mut pages = Vec::new();
+    let mut start = 0;
+    let mut used = 0.0;
+    let mut count = 0;
+
+    for (index, width) in widths.into_iter().enumerate() {
+        if index > start && used + width > available {
+            pages.push(start..index);
+            start = index;
+            used = 0.0;
+        }
+
+        used += width;
+        count = index + 1;
+    }
+
+    if count > start {
+        pages.push(start..count);
+    }
+
+    pages
+}
+
+/// Splits a number of items into pages of a fixed number of lines
+pub fn paginate_lines(count: usize, lines: usize) -> Vec<Range<usize>> {
+    (0..count)
+        .step_by(lines.max(1))
+        .map(|start| start..(start + lines).min(count))
+        .collect()
+}
+
+/// The index of the page containing the item, defaulting to the first page
+pub fn page_of(pages: &[Range<usize>], index: usize) -> usize {
+    pages
+        .iter()
+        .position(|page| page.contains(&index))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paginate_fills_pages_greedily() {
+        let pages = paginate([40.0, 40.0, 40.0, 70.0, 10.0], 100.0);
+
+        assert_eq!(pages, vec![0..2, 2..3, 3..5]);
+    }
+
+    #[test]
+    fn paginate_gives_wide_items_their_own_page() {
+        let pages = paginate([150.0, 20.0], 100.0);
+
+        assert_eq!(pages, vec![0..1, 1..2]);
+    }
+
+    #[test]
+    fn paginate_without_items_has_no_pages() {
+        assert!(paginate([], 100.0).is_empty());
+        assert!(paginate_lines(0, 5).is_empty());
+    }
+
+    #[test]
+    fn paginate_lines_splits_by_count() {
+        let pages = paginate_lines(7, 3);
+
+        assert_eq!(pages, vec![0..3, 3..6, 6..7]);
+        assert_eq!(page_of(&pages, 4), 1);
+    }
+}
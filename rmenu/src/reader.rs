@@ -0,0 +1,102 @@
+use std::{
+    io::BufRead,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use anyhow::{Context, Result};
+
+use crate::{filter::Filter, item::Item};
+
+/// Reads items separated by the delimiter on a background thread, sending each one as soon
+/// as it is read. The channel is closed once the input ends or after the first error
+pub fn read_items(
+    reader: impl BufRead + Send + 'static,
+    delimiter: u8,
+    filter: Filter,
+    on_item: impl Fn() + Send + 'static,
+) -> Receiver<Result<Item>> {
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        for bytes in reader.split(delimiter) {
+            let item = bytes
+                .context("failed to read item")
+                .and_then(|bytes| String::from_utf8(bytes).context("failed to read item as UTF-8"))
+                .and_then(|mut text| {
+                    if delimiter == b'\n' && text.ends_with('\r') {
+                        text.pop();
+                    }
+                    filter.to_item(text)
+                });
+            let failed = item.is_err();
+
+            if tx.send(item).is_err() {
+                break;
+            }
+
+            on_item();
+
+            if failed {
+                break;
+            }
+        }
+    });
+
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::fields::Fields;
+
+    #[test]
+    fn read_items_sends_items_until_the_end() -> Result<()> {
+        let rx = read_items(
+            Cursor::new("a\r\nb\n"),
+            b'\n',
+            Filter::new(&None, Fields::default()),
+            || {},
+        );
+
+        let keys = rx
+            .iter()
+            .map(|item| item.map(|item| item.key))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(keys, ["a", "b"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_items_splits_on_the_delimiter() -> Result<()> {
+        let rx = read_items(
+            Cursor::new("a\nb\0c"),
+            b'\0',
+            Filter::new(&None, Fields::default()),
+            || {},
+        );
+
+        let values = rx
+            .iter()
+            .map(|item| item.map(|item| item.value))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(values, ["a\nb", "c"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_items_stops_at_the_first_error() {
+        let filter = Filter::new(&Some(String::from(r#""name""#)), Fields::default());
+        let input = [r#"{"name":"a"}"#, "not json", r#"{"name":"b"}"#].join("\n");
+        let rx = read_items(Cursor::new(input), b'\n', filter, || {});
+
+        let results: Vec<_> = rx.iter().collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[1].is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use anyhow::{Context, Result};
+use egui::{Pos2, Rect};
+
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod x11;
+
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(all(unix, not(target_os = "macos")))]
+use x11 as platform;
+
+/// A monitor and the area it covers on the desktop
+pub struct Monitor {
+    pub name: String,
+    /// The area in the units of the platform, which are pixels on X11
+    pub rect: Rect,
+    /// The scale factor of windows on the monitor, from logical points to the units of `rect`
+    pub scale_factor: f32,
+    /// Whether this is the monitor used when none is selected
+    pub main: bool,
+}
+
+impl Monitor {
+    /// The area in the logical points windows are placed and sized in
+    pub fn logical_rect(&self) -> Rect {
+        Rect::from_min_size(
+            (self.rect.min.to_vec2() / self.scale_factor).to_pos2(),
+            self.rect.size() / self.scale_factor,
+        )
+    }
+}
+
+/// The ways a monitor can be selected from the command line
+#[derive(Clone)]
+pub enum MonitorSelection {
+    Index(usize),
+    Name(String),
+    Focused,
+    Pointer,
+}
+
+/// Finds the selected monitor, or the main monitor if none is selected
+pub fn find_monitor(selection: Option<&MonitorSelection>) -> Result<Monitor> {
+    let screen = platform::Screen::connect()?;
+    let monitors = screen.monitors()?;
+
+    let position = match selection {
+        Some(MonitorSelection::Focused) => screen.focused_position()?,
+        Some(MonitorSelection::Pointer) => screen.pointer_position()?,
+        _ => None,
+    };
+
+    choose_monitor(monitors, selection, position)
+}
+
+fn choose_monitor(
+    monitors: Vec<Monitor>,
+    selection: Option<&MonitorSelection>,
+    position: Option<Pos2>,
+) -> Result<Monitor> {
+    match selection {
+        Some(MonitorSelection::Index(index)) => monitors
+            .into_iter()
+            .nth(*index)
+            .with_context(|| format!("failed to find a monitor with index {index}")),
+        Some(MonitorSelection::Name(name)) => monitors
+            .into_iter()
+            .find(|monitor| &monitor.name == name)
+            .with_context(|| format!("failed to find a monitor named {name}")),
+        _ => {
+            let index = position
+                .and_then(|position| {
+                    monitors
+                        .iter()
+                        .position(|monitor| monitor.rect.contains(position))
+                })
+                .or_else(|| monitors.iter().position(|monitor| monitor.main))
+                .unwrap_or(0);
+
+            monitors
+                .into_iter()
+                .nth(index)
+                .context("failed to find any monitors")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::Vec2;
+
+    fn monitors() -> Vec<Monitor> {
+        vec![
+            Monitor {
+                name: "DP-1".to_string(),
+                rect: Rect::from_min_size(Pos2::new(0.0, 0.0), Vec2::new(1920.0, 1080.0)),
+                scale_factor: 1.0,
+                main: false,
+            },
+            Monitor {
+                name: "DP-2".to_string(),
+                rect: Rect::from_min_size(Pos2::new(1920.0, 0.0), Vec2::new(2560.0, 1440.0)),
+                scale_factor: 2.0,
+                main: true,
+            },
+        ]
+    }
+
+    #[test]
+    fn choose_monitor_defaults_to_main() -> Result<()> {
+        let monitor = choose_monitor(monitors(), None, None)?;
+
+        assert_eq!(monitor.name, "DP-2");
+
+        Ok(())
+    }
+
+    #[test]
+    fn choose_monitor_by_index_and_name() -> Result<()> {
+        let selection = MonitorSelection::Index(0);
+        assert_eq!(
+            choose_monitor(monitors(), Some(&selection), None)?.name,
+            "DP-1"
+        );
+
+        let selection = MonitorSelection::Name("DP-2".to_string());
+        assert_eq!(
+            choose_monitor(monitors(), Some(&selection), None)?.name,
+            "DP-2"
+        );
+
+        let selection = MonitorSelection::Index(2);
+        assert!(choose_monitor(monitors(), Some(&selection), None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn choose_monitor_containing_position() -> Result<()> {
+        let selection = MonitorSelection::Pointer;
+        let position = Some(Pos2::new(100.0, 100.0));
+
+        let monitor = choose_monitor(monitors(), Some(&selection), position)?;
+
+        assert_eq!(monitor.name, "DP-1");
+
+        Ok(())
+    }
+
+    #[test]
+    fn logical_rect_divides_by_the_scale_factor() {
+        let monitor = &monitors()[1];
+
+        assert_eq!(
+            monitor.logical_rect(),
+            Rect::from_min_size(Pos2::new(960.0, 0.0), Vec2::new(1280.0, 720.0))
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use std::ffi::CStr;
+
+use anyhow::Result;
+use cocoa::{
+    appkit::{NSEvent, NSScreen},
+    base::{id, nil},
+    foundation::{NSArray, NSRect, NSString},
+};
+use egui::{Pos2, Rect, Vec2};
+use objc::{msg_send, sel, sel_impl};
+
+use super::Monitor;
+
+pub struct Screen {
+    /// Cocoa places the origin at the bottom left of the primary screen, with y growing upwards
+    primary_height: f64,
+}
+
+impl Screen {
+    pub fn connect() -> Result<Self> {
+        let primary_height = unsafe {
+            let screens = NSScreen::screens(nil);
+            screens.objectAtIndex(0).frame().size.height
+        };
+
+        Ok(Self { primary_height })
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn rect(&self, frame: NSRect) -> Rect {
+        Rect::from_min_size(
+            Pos2::new(
+                frame.origin.x as f32,
+                (self.primary_height - frame.origin.y - frame.size.height) as f32,
+            ),
+            Vec2::new(frame.size.width as f32, frame.size.height as f32),
+        )
+    }
+
+    pub fn monitors(&self) -> Result<Vec<Monitor>> {
+        let monitors = unsafe {
+            let main_frame = NSScreen::mainScreen(nil).frame();
+            let screens = NSScreen::screens(nil);
+
+            (0..screens.count())
+                .map(|index| {
+                    let screen = screens.objectAtIndex(index);
+                    let frame = screen.frame();
+                    let name: id = msg_send![screen, localizedName];
+
+                    Monitor {
+                        name: CStr::from_ptr(name.UTF8String())
+                            .to_string_lossy()
+                            .into_owned(),
+                        rect: self.rect(frame),
+                        // Frames are already given in points
+                        scale_factor: 1.0,
+                        main: frame.origin.x == main_frame.origin.x
+                            && frame.origin.y == main_frame.origin.y,
+                    }
+                })
+                .collect()
+        };
+
+        Ok(monitors)
+    }
+
+    /// The main screen is the one containing the focused window
+    pub fn focused_position(&self) -> Result<Option<Pos2>> {
+        let frame = unsafe { NSScreen::mainScreen(nil).frame() };
+
+        Ok(Some(self.rect(frame).center()))
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn pointer_position(&self) -> Result<Option<Pos2>> {
+        let location = unsafe { NSEvent::mouseLocation(nil) };
+
+        Ok(Some(Pos2::new(
+            location.x as f32,
+            (self.primary_height - location.y) as f32,
+        )))
+    }
+}
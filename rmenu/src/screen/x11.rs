@@ -0,0 +1,315 @@
+use std::env;
+
+use anyhow::{Context, Result};
+use egui::{Pos2, Rect, Vec2};
+use x11rb::{
+    connection::{Connection, RequestConnection},
+    protocol::{
+        randr::{self, ConnectionExt as _},
+        xinerama::{self, ConnectionExt as _},
+        xproto::{AtomEnum, ConnectionExt as _, Screen as X11Screen},
+    },
+    rust_connection::RustConnection,
+};
+
+use super::Monitor;
+
+fn rect(x: i16, y: i16, width: u16, height: u16) -> Rect {
+    Rect::from_min_size(
+        Pos2::new(f32::from(x), f32::from(y)),
+        Vec2::new(f32::from(width), f32::from(height)),
+    )
+}
+
+/// The scale factor winit derives from the density of a monitor, in steps of 1/12
+fn density_scale_factor((width, height): (u16, u16), (width_mm, height_mm): (u32, u32)) -> f32 {
+    if width_mm == 0 || height_mm == 0 {
+        return 1.0;
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    let pixels_per_mm =
+        ((f32::from(width) * f32::from(height)) / (width_mm as f32 * height_mm as f32)).sqrt();
+    let scale_factor = ((pixels_per_mm * 12.0 * 25.4 / 96.0).round() / 12.0).max(1.0);
+
+    if scale_factor <= 20.0 {
+        scale_factor
+    } else {
+        1.0
+    }
+}
+
+/// The DPI set by `Xft.dpi` in the resource database
+fn parse_xft_dpi(resources: &str) -> Option<f32> {
+    resources
+        .lines()
+        .find_map(|line| line.strip_prefix("Xft.dpi:\t"))?
+        .parse()
+        .ok()
+}
+
+/// The scale factor winit gives windows on a monitor: the one set by `WINIT_X11_SCALE_FACTOR`,
+/// else the one set by `Xft.dpi`, else the one from the density of the monitor
+fn scale_factor(
+    env_scale_factor: &str,
+    xft_scale_factor: Option<f32>,
+    size: (u16, u16),
+    size_mm: (u32, u32),
+) -> f32 {
+    if env_scale_factor.eq_ignore_ascii_case("randr") {
+        return density_scale_factor(size, size_mm);
+    }
+
+    match env_scale_factor.parse::<f32>() {
+        Ok(scale_factor) if scale_factor.is_normal() && scale_factor > 0.0 => scale_factor,
+        _ => xft_scale_factor.unwrap_or_else(|| density_scale_factor(size, size_mm)),
+    }
+}
+
+pub struct Screen {
+    conn: RustConnection,
+    screen_num: usize,
+}
+
+impl Screen {
+    pub fn connect() -> Result<Self> {
+        let (conn, screen_num) =
+            x11rb::connect(None).context("failed to connect to the X server")?;
+
+        Ok(Self { conn, screen_num })
+    }
+
+    fn screen(&self) -> &X11Screen {
+        &self.conn.setup().roots[self.screen_num]
+    }
+
+    /// The scale factor for a monitor of the given size in pixels and millimeters
+    fn scale_factor(&self, size: (u16, u16), size_mm: (u32, u32)) -> Result<f32> {
+        let resources = self
+            .conn
+            .get_property(
+                false,
+                self.screen().root,
+                AtomEnum::RESOURCE_MANAGER,
+                AtomEnum::STRING,
+                0,
+                u32::MAX,
+            )?
+            .reply()?
+            .value;
+        let xft_scale_factor =
+            parse_xft_dpi(&String::from_utf8_lossy(&resources)).map(|dpi| dpi / 96.0);
+
+        Ok(scale_factor(
+            &env::var("WINIT_X11_SCALE_FACTOR").unwrap_or_default(),
+            xft_scale_factor,
+            size,
+            size_mm,
+        ))
+    }
+
+    /// The size of the whole screen in millimeters, for monitors without their own
+    fn screen_size_mm(&self, size: (u16, u16)) -> (u32, u32) {
+        let screen = self.screen();
+
+        // Scaled to the size of the monitor so its density is the one of the screen
+        let scale = |mm: u16, pixels: u16, screen_pixels: u16| {
+            u32::from(mm) * u32::from(pixels) / u32::from(screen_pixels.max(1))
+        };
+
+        (
+            scale(screen.width_in_millimeters, size.0, screen.width_in_pixels),
+            scale(
+                screen.height_in_millimeters,
+                size.1,
+                screen.height_in_pixels,
+            ),
+        )
+    }
+
+    /// Lists the monitors known to the randr extension (1.5 or newer)
+    fn randr_monitors(&self) -> Result<Option<Vec<Monitor>>> {
+        if self
+            .conn
+            .extension_information(randr::X11_EXTENSION_NAME)?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        let version = self.conn.randr_query_version(1, 5)?.reply()?;
+        if (version.major_version, version.minor_version) < (1, 5) {
+            return Ok(None);
+        }
+
+        let infos = self
+            .conn
+            .randr_get_monitors(self.screen().root, true)?
+            .reply()
+            .context("failed to get monitors from randr")?
+            .monitors;
+
+        let monitors = infos
+            .into_iter()
+            .map(|info| {
+                let name = self.conn.get_atom_name(info.name)?.reply()?.name;
+
+                Ok(Monitor {
+                    name: String::from_utf8_lossy(&name).into_owned(),
+                    rect: rect(info.x, info.y, info.width, info.height),
+                    scale_factor: self.scale_factor(
+                        (info.width, info.height),
+                        (info.width_in_millimeters, info.height_in_millimeters),
+                    )?,
+                    main: info.primary,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Some(monitors))
+    }
+
+    /// Lists the screens known to the xinerama extension, which has no notion of names
+    /// or a primary screen
+    fn xinerama_monitors(&self) -> Result<Option<Vec<Monitor>>> {
+        if self
+            .conn
+            .extension_information(xinerama::X11_EXTENSION_NAME)?
+            .is_none()
+            || self.conn.xinerama_is_active()?.reply()?.state == 0
+        {
+            return Ok(None);
+        }
+
+        let monitors = self
+            .conn
+            .xinerama_query_screens()?
+            .reply()
+            .context("failed to query screens from xinerama")?
+            .screen_info
+            .into_iter()
+            .enumerate()
+            .map(|(index, info)| {
+                let size = (info.width, info.height);
+
+                Ok(Monitor {
+                    name: index.to_string(),
+                    rect: rect(info.x_org, info.y_org, info.width, info.height),
+                    scale_factor: self.scale_factor(size, self.screen_size_mm(size))?,
+                    main: index == 0,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Some(monitors))
+    }
+
+    pub fn monitors(&self) -> Result<Vec<Monitor>> {
+        let monitors = match self.randr_monitors()? {
+            Some(monitors) if !monitors.is_empty() => monitors,
+            _ => self.xinerama_monitors()?.unwrap_or_default(),
+        };
+
+        if monitors.is_empty() {
+            // Without either extension the root window spans the whole screen
+            let screen = self.screen();
+            let size = (screen.width_in_pixels, screen.height_in_pixels);
+
+            return Ok(vec![Monitor {
+                name: self.screen_num.to_string(),
+                rect: rect(0, 0, size.0, size.1),
+                scale_factor: self.scale_factor(size, self.screen_size_mm(size))?,
+                main: true,
+            }]);
+        }
+
+        Ok(monitors)
+    }
+
+    /// The center of the window in `_NET_ACTIVE_WINDOW`, if the window manager sets it
+    pub fn focused_position(&self) -> Result<Option<Pos2>> {
+        let root = self.screen().root;
+        let active_window = self
+            .conn
+            .intern_atom(true, b"_NET_ACTIVE_WINDOW")?
+            .reply()?
+            .atom;
+
+        // The atom does not exist when no EWMH compliant window manager is running
+        if active_window == x11rb::NONE {
+            return Ok(None);
+        }
+
+        let window = self
+            .conn
+            .get_property(false, root, active_window, AtomEnum::WINDOW, 0, 1)?
+            .reply()?
+            .value32()
+            .and_then(|mut values| values.next())
+            .filter(|window| *window != 0);
+
+        let Some(window) = window else {
+            return Ok(None);
+        };
+
+        let geometry = self.conn.get_geometry(window)?.reply()?;
+        let origin = self
+            .conn
+            .translate_coordinates(window, root, 0, 0)?
+            .reply()?;
+
+        Ok(Some(
+            rect(origin.dst_x, origin.dst_y, geometry.width, geometry.height).center(),
+        ))
+    }
+
+    pub fn pointer_position(&self) -> Result<Option<Pos2>> {
+        let pointer = self.conn.query_pointer(self.screen().root)?.reply()?;
+
+        Ok(Some(Pos2::new(
+            f32::from(pointer.root_x),
+            f32::from(pointer.root_y),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_factor_follows_winit() {
+        assert_eq!(
+            parse_xft_dpi("Xft.antialias:\t1\nXft.dpi:\t192\n"),
+            Some(192.0)
+        );
+        assert_eq!(parse_xft_dpi("Xft.antialias:\t1\n"), None);
+
+        // A 27" 4K monitor
+        let size = (3840, 2160);
+        let size_mm = (597, 336);
+        assert!((density_scale_factor(size, size_mm) - 20.0 / 12.0).abs() < f32::EPSILON);
+        assert!((density_scale_factor(size, (0, 0)) - 1.0).abs() < f32::EPSILON);
+
+        assert!((scale_factor("2", Some(1.5), size, size_mm) - 2.0).abs() < f32::EPSILON);
+        assert!((scale_factor("", Some(1.5), size, size_mm) - 1.5).abs() < f32::EPSILON);
+        assert!(
+            (scale_factor("randr", Some(1.5), size, size_mm) - 20.0 / 12.0).abs() < f32::EPSILON
+        );
+        assert!((scale_factor("", None, size, size_mm) - 20.0 / 12.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    #[ignore = "requires an X server, run with `xvfb-run cargo test -- --ignored`"]
+    fn monitors_have_a_size() -> Result<()> {
+        let monitors = Screen::connect()?.monitors()?;
+
+        assert!(!monitors.is_empty());
+        for monitor in monitors {
+            assert!(monitor.rect.width() > 0.0);
+            assert!(monitor.rect.height() > 0.0);
+        }
+
+        Ok(())
+    }
+}
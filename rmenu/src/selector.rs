@@ -1,10 +1,25 @@
-use std::sync::{mpsc::Sender, Arc};
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    ops::Range,
+    sync::{
+        mpsc::{Receiver, Sender, TryRecvError},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
 
+use crate::history::History;
+use crate::input::Input;
 use crate::item_filter::ItemFilter;
-use crate::item_label::ItemLabelExt;
+use crate::item_label::{ItemColors, ItemLabelExt};
+use crate::keymap::{AcceptKey, Action, Keymap};
+use crate::paging::{page_of, paginate, paginate_lines};
 use egui::{
-    CentralPanel, Color32, Context, Event, FontDefinitions, FontId, Frame, Key, Margin, Modifiers,
-    RichText, Style, Ui, Vec2, Visuals,
+    Align, CentralPanel, Color32, Context, Event, FontDefinitions, FontId, Frame, Label, Layout,
+    Margin, RichText, Stroke, Style, Ui, Vec2, Visuals,
 };
 
 use crate::item::Item;
@@ -14,6 +29,10 @@ pub struct AppColors {
     pub background_normal: Color32,
     pub foreground_selection: Color32,
     pub background_selection: Color32,
+    pub foreground_match: Color32,
+    pub foreground_marked: Color32,
+    pub foreground_prompt: Color32,
+    pub border: Option<Color32>,
 }
 
 pub struct AppFont {
@@ -21,14 +40,147 @@ pub struct AppFont {
     pub definitions: FontDefinitions,
 }
 
+pub struct AppLayout {
+    pub input_min_width: f32,
+    /// The number of items listed below the input, or zero to list them next to it
+    pub lines: usize,
+    pub line_height: f32,
+}
+
+pub struct AppOptions {
+    /// Whether several items can be marked and accepted at once
+    pub multi: bool,
+    pub keymap: Keymap,
+    /// The text the input starts with
+    pub query: String,
+    pub preselect: Option<Preselect>,
+    /// The history ranking items with equal matches
+    pub history: Option<Arc<History>>,
+}
+
+/// The item to select on startup, selected once it has been read
+pub enum Preselect {
+    /// The first item whose text or input line equals the string
+    Value(String),
+    /// The item read from the zero-based line of the input
+    Index(usize),
+}
+
+impl Preselect {
+    fn matches(&self, index: usize, item: &Item) -> bool {
+        match self {
+            Self::Value(value) => item.key == *value || item.value == *value,
+            Self::Index(preselected) => index == *preselected,
+        }
+    }
+}
+
+/// An accepted item along with its position in the input
+pub struct AcceptedItem {
+    /// The zero-based line of the input the item was read from,
+    /// `None` when the query itself was accepted
+    pub index: Option<usize>,
+    pub item: Item,
+}
+
+/// What was accepted in the selector
+pub struct Accepted {
+    pub items: Vec<AcceptedItem>,
+    /// The text of the input when accepting
+    pub query: String,
+    /// The accept key used, `None` when accepted with the accept action
+    pub accept_key: Option<AcceptKey>,
+}
+
+/// The frames of the indicator shown while items are still being read
+const LOADING_FRAMES: [&str; 4] = ["-", "\\", "|", "/"];
+
+/// A request from the selector to the window showing it
+pub enum WindowRequest {
+    Focus,
+    Close,
+}
+
+/// An item passing the filter, with the positions of the chars it matched on
+struct VisibleItem {
+    /// The index of the item in the input
+    index: usize,
+    score: i64,
+    frecency: f64,
+    positions: Vec<usize>,
+}
+
+impl VisibleItem {
+    /// Orders the best ranked item first, by score and then by frecency
+    fn rank(&self, other: &Self) -> Ordering {
+        other
+            .score
+            .cmp(&self.score)
+            .then_with(|| other.frecency.total_cmp(&self.frecency))
+    }
+}
+
+/// The items passing the filter for a query, ranked in the order they are shown
+#[derive(Default)]
+struct VisibleItems {
+    query: String,
+    /// The number of items read which have been filtered
+    filtered: usize,
+    items: Vec<VisibleItem>,
+}
+
+/// Merges two ranked lists, keeping items of the first before equally ranked ones of the second
+fn merge_ranked(first: Vec<VisibleItem>, second: Vec<VisibleItem>) -> Vec<VisibleItem> {
+    let mut merged = Vec::with_capacity(first.len() + second.len());
+    let mut first = first.into_iter().peekable();
+    let mut second = second.into_iter().peekable();
+
+    loop {
+        let next = match (first.peek(), second.peek()) {
+            (Some(a), Some(b)) if a.rank(b) == Ordering::Greater => second.next(),
+            (Some(_), _) => first.next(),
+            (None, _) => second.next(),
+        };
+
+        match next {
+            Some(visible_item) => merged.push(visible_item),
+            None => return merged,
+        }
+    }
+}
+
 pub struct Selector {
-    text: String,
+    input: Input,
     items: Vec<Item>,
+    items_receiver: Receiver<Result<Item>>,
+    visible_items: VisibleItems,
+    /// The widths of the items listed next to the input, measured once they are visible
+    item_widths: Vec<Option<f32>>,
+    loading: bool,
     selection: usize,
     prompt: String,
     item_filter: Arc<dyn ItemFilter>,
     colors: AppColors,
-    sender: Sender<Option<String>>,
+    layout: AppLayout,
+    options: AppOptions,
+    marks: HashSet<usize>,
+    /// The item still to be selected, until found or the user moves on
+    preselect: Option<Preselect>,
+    sender: Sender<Result<Option<Accepted>>>,
+}
+
+/// The horizontal space on each side of an item
+const ITEM_PADDING: f32 = 5.0;
+
+fn text_width(ui: &Ui, text: &str) -> f32 {
+    let font_id = ui.style().override_font_id.clone().unwrap_or_default();
+
+    ui.fonts(|fonts| {
+        fonts
+            .layout_no_wrap(text.to_string(), font_id, Color32::WHITE)
+            .size()
+            .x
+    })
 }
 
 fn clamp(low: usize, value: usize, high: usize) -> usize {
@@ -42,20 +194,18 @@ fn clamp(low: usize, value: usize, high: usize) -> usize {
 }
 
 impl Selector {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        cc: &eframe::CreationContext<'_>,
-        items: Vec<Item>,
+        ctx: &Context,
+        items_receiver: Receiver<Result<Item>>,
         item_filter: Arc<dyn ItemFilter>,
         prompt: String,
         colors: AppColors,
         font: AppFont,
-        sender: Sender<Option<String>>,
+        layout: AppLayout,
+        mut options: AppOptions,
+        sender: Sender<Result<Option<Accepted>>>,
     ) -> Self {
-        // Customize egui here with cc.egui_ctx.set_fonts and cc.egui_ctx.set_visuals.
-        // Restore app state using cc.storage (requires the "persistence" feature).
-        // Use the cc.gl (a glow::Context) to create graphics shaders and buffers that you can use
-        // for e.g. egui::PaintCallback.
-
         let visuals = Visuals {
             panel_fill: colors.background_normal,
             override_text_color: Some(colors.foreground_normal),
@@ -68,97 +218,454 @@ impl Selector {
             ..Default::default()
         };
 
-        cc.egui_ctx.set_fonts(font.definitions);
-        cc.egui_ctx.set_style(style);
+        ctx.set_fonts(font.definitions);
+        ctx.set_style(style);
+
+        let mut input = Input::default();
+        input.insert(&options.query);
+        let preselect = options.preselect.take();
 
         Self {
-            text: String::new(),
-            items,
+            input,
+            items: Vec::new(),
+            items_receiver,
+            visible_items: VisibleItems::default(),
+            item_widths: Vec::new(),
+            loading: true,
             prompt,
             selection: 0,
             item_filter,
             colors,
+            layout,
+            options,
+            marks: HashSet::new(),
+            preselect,
             sender,
         }
     }
 
-    fn visible_items(&self) -> Vec<Item> {
-        //TODO: Can we memoize this based on the search text
-        self.items
+    /// Takes the items read since the last frame, closing the selector if reading failed
+    fn receive_items(&mut self) -> Option<WindowRequest> {
+        loop {
+            match self.items_receiver.try_recv() {
+                Ok(Ok(item)) => self.items.push(item),
+                Ok(Err(err)) => {
+                    let _ = self.sender.send(Err(err));
+                    return Some(WindowRequest::Close);
+                }
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => {
+                    self.loading = false;
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Selects the preselected item if it has been read, giving up once all items are read
+    fn apply_preselect(&mut self) {
+        let Some(preselect) = &self.preselect else {
+            return;
+        };
+
+        if let Some(position) = self.visible_items.items.iter().position(|visible_item| {
+            preselect.matches(visible_item.index, &self.items[visible_item.index])
+        }) {
+            self.selection = position;
+            self.preselect = None;
+        } else if !self.loading {
+            self.preselect = None;
+        }
+    }
+
+    /// Brings the visible items up to date with the query and the items read,
+    /// only filtering the items read since the last update if the query is unchanged
+    fn filter_items(&mut self) {
+        if self.visible_items.query != self.input.text() {
+            self.visible_items = VisibleItems {
+                query: self.input.text().to_string(),
+                ..VisibleItems::default()
+            };
+        }
+
+        let new_items = &self.items[self.visible_items.filtered..];
+        if new_items.is_empty() {
+            return;
+        }
+
+        let mut visible_items: Vec<VisibleItem> = new_items
             .iter()
-            .filter(|Item { key, .. }| self.item_filter.filter(&self.text, key))
-            .cloned()
-            .collect()
+            .zip(self.visible_items.filtered..)
+            .filter_map(|(item, index)| {
+                let item_match = self.item_filter.filter(self.input.text(), &item.search)?;
+                let frecency = self
+                    .options
+                    .history
+                    .as_ref()
+                    .map_or(0.0, |history| history.frecency(&item.key));
+
+                Some(VisibleItem {
+                    index,
+                    score: item_match.score,
+                    frecency,
+                    positions: item.key_positions(item_match.positions),
+                })
+            })
+            .collect();
+
+        // The sort and merge are stable so equally ranked items keep their input order
+        visible_items.sort_by(VisibleItem::rank);
+        self.visible_items.items =
+            merge_ranked(std::mem::take(&mut self.visible_items.items), visible_items);
+        self.visible_items.filtered = self.items.len();
     }
 
     fn is_selection(&self, index: usize) -> bool {
         self.selection == index
     }
 
-    fn selected_item_value(&self) -> Option<String> {
-        self.visible_items()
-            .get(self.selection)
-            .map(|item| item.value.clone())
+    fn item_colors(&self, index: usize, visible_item: &VisibleItem) -> ItemColors {
+        let (mut foreground, background) = if self.is_selection(index) {
+            (
+                Some(self.colors.foreground_selection),
+                Some(self.colors.background_selection),
+            )
+        } else {
+            (None, None)
+        };
+
+        if self.marks.contains(&visible_item.index) {
+            foreground = Some(self.colors.foreground_marked);
+        }
+
+        ItemColors {
+            foreground,
+            background,
+            matched: self.colors.foreground_match,
+        }
+    }
+
+    fn input_text(&self) -> String {
+        format!("{} {}", self.prompt, self.input.text())
+    }
+
+    /// Draws the prompt and input text with a cursor
+    fn show_input(&self, ui: &mut Ui) {
+        ui.label(RichText::new(&self.prompt).color(self.colors.foreground_prompt));
+        let response = ui.label(format!(" {}", self.input.text()));
+
+        let before_cursor = &self.input.text()[..self.input.cursor()];
+        let x = response.rect.min.x + text_width(ui, &format!(" {before_cursor}"));
+        let stroke = Stroke::new(1.0, ui.visuals().text_color());
+        ui.painter().vline(x, response.rect.y_range(), stroke);
+
+        if self.loading {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let frame = (ui.input(|input| input.time) * 10.0) as usize % LOADING_FRAMES.len();
+            ui.label(format!(" {}", LOADING_FRAMES[frame]));
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+        }
+    }
+
+    fn indicator_width(ui: &Ui) -> f32 {
+        text_width(ui, "<").max(text_width(ui, ">")) + 2.0 * ITEM_PADDING
+    }
+
+    /// Splits the visible items into the pages shown one at a time,
+    /// either by the number of lines or by the width left next to the input
+    fn pages(&mut self, ui: &Ui) -> Vec<Range<usize>> {
+        if self.layout.lines > 0 {
+            return paginate_lines(self.visible_items.items.len(), self.layout.lines);
+        }
+
+        let input_width = text_width(ui, &self.input_text()).max(self.layout.input_min_width);
+        let available = ui.available_width() - input_width - 2.0 * Self::indicator_width(ui);
+
+        let Self {
+            items,
+            visible_items,
+            item_widths,
+            ..
+        } = self;
+        item_widths.resize(items.len(), None);
+
+        paginate(
+            visible_items.items.iter().map(|visible_item| {
+                *item_widths[visible_item.index].get_or_insert_with(|| {
+                    text_width(ui, &items[visible_item.index].key) + 2.0 * ITEM_PADDING
+                })
+            }),
+            available,
+        )
+    }
+
+    fn accepted_item(&self, index: usize) -> AcceptedItem {
+        AcceptedItem {
+            index: Some(index),
+            item: self.items[index].clone(),
+        }
     }
 
-    fn handle_input(&mut self, ui: &mut Ui, frame: &mut eframe::Frame) {
-        let visible_items = self.visible_items();
+    /// The marked items in input order, or the selected item if none are marked
+    fn accepted_items(&self) -> Vec<AcceptedItem> {
+        if self.marks.is_empty() {
+            return self
+                .visible_items
+                .items
+                .get(self.selection)
+                .map(|visible_item| self.accepted_item(visible_item.index))
+                .into_iter()
+                .collect();
+        }
+
+        let mut marks: Vec<usize> = self.marks.iter().copied().collect();
+        marks.sort_unstable();
+
+        marks
+            .into_iter()
+            .map(|index| self.accepted_item(index))
+            .collect()
+    }
+
+    /// Sends the accepted items, which are empty when no item is selected
+    fn accept(&self, accept_key: Option<AcceptKey>) -> WindowRequest {
+        let accepted = Accepted {
+            items: self.accepted_items(),
+            query: self.input.text().to_string(),
+            accept_key,
+        };
+        let _ = self.sender.send(Ok(Some(accepted)));
+
+        WindowRequest::Close
+    }
+
+    /// Sends the query itself as the accepted item
+    fn accept_query(&self) -> WindowRequest {
+        let query = self.input.text().to_string();
+        let accepted = Accepted {
+            items: vec![AcceptedItem {
+                index: None,
+                item: Item {
+                    key: query.clone(),
+                    search: query.clone(),
+                    search_fields: Vec::new(),
+                    value: query.clone(),
+                },
+            }],
+            query,
+            accept_key: None,
+        };
+        let _ = self.sender.send(Ok(Some(accepted)));
+
+        WindowRequest::Close
+    }
+
+    /// Toggles the mark on the selected item and moves the selection by one in the given direction
+    fn toggle_mark(&mut self, forward: bool) {
+        if !self.options.multi {
+            return;
+        }
+
+        let visible_items = &self.visible_items.items;
+        let Some(visible_item) = visible_items.get(self.selection) else {
+            return;
+        };
+
+        if !self.marks.remove(&visible_item.index) {
+            self.marks.insert(visible_item.index);
+        }
+
+        let max = visible_items.len().saturating_sub(1);
+        self.selection = if forward {
+            clamp(0, self.selection + 1, max)
+        } else {
+            self.selection.saturating_sub(1)
+        };
+    }
+
+    /// Changes the input, selecting the first item as the visible items may change
+    fn edit_input(&mut self, edit: impl FnOnce(&mut Input)) {
+        self.selection = 0;
+        self.preselect = None;
+        edit(&mut self.input);
+    }
+
+    fn perform(&mut self, action: Action, pages: &[Range<usize>]) -> Option<WindowRequest> {
+        self.preselect = None;
+
+        let page = page_of(pages, self.selection);
+        let max = self.visible_items.items.len().saturating_sub(1);
+
+        // Items listed next to the input are navigated with the keys moving the cursor,
+        // which is then moved with their Ctrl variants
+        let navigating = self.layout.lines == 0;
+        let action = match action {
+            Action::Left if navigating => Action::Prev,
+            Action::Left => Action::BackwardChar,
+            Action::Right if navigating => Action::Next,
+            Action::Right => Action::ForwardChar,
+            Action::Home if navigating => Action::First,
+            Action::Home => Action::BeginningOfLine,
+            Action::End if navigating => Action::Last,
+            Action::End => Action::EndOfLine,
+            action => action,
+        };
+
+        match action {
+            Action::Cancel => {
+                let _ = self.sender.send(Ok(None));
+                return Some(WindowRequest::Close);
+            }
+            Action::Accept => {
+                return Some(self.accept(None));
+            }
+            Action::AcceptQuery => {
+                return Some(self.accept_query());
+            }
+            Action::Prev => {
+                self.selection = self
+                    .selection
+                    .checked_sub(1)
+                    .map_or(0, |i| clamp(0, i, max));
+            }
+            Action::Next => {
+                self.selection = self
+                    .selection
+                    .checked_add(1)
+                    .map_or(max, |i| clamp(0, i, max));
+            }
+            Action::PageUp => {
+                self.selection = page
+                    .checked_sub(1)
+                    .and_then(|page| pages.get(page))
+                    .map_or(0, |page| page.start);
+            }
+            Action::PageDown => {
+                if let Some(next_page) = pages.get(page + 1) {
+                    self.selection = next_page.start;
+                }
+            }
+            Action::First => self.selection = 0,
+            Action::Last => self.selection = max,
+            Action::ToggleMark => self.toggle_mark(true),
+            Action::ToggleMarkPrev => self.toggle_mark(false),
+            Action::BackwardChar => self.input.move_left(),
+            Action::ForwardChar => self.input.move_right(),
+            Action::BeginningOfLine => self.input.move_to_start(),
+            Action::EndOfLine => self.input.move_to_end(),
+            Action::BackwardDeleteChar => self.edit_input(Input::delete_backward),
+            Action::DeleteChar => self.edit_input(Input::delete_forward),
+            Action::BackwardKillWord => self.edit_input(Input::delete_word),
+            Action::KillLine => self.edit_input(Input::kill_line),
+            Action::KillToEnd => self.edit_input(Input::kill_to_end),
+            Action::ClearInput => self.edit_input(|input| *input = Input::default()),
+            Action::Left | Action::Right | Action::Home | Action::End => {}
+        }
+
+        None
+    }
+
+    fn handle_input(&mut self, ui: &mut Ui, pages: &[Range<usize>]) -> Option<WindowRequest> {
+        let mut request = None;
 
         ui.input(|input| {
             for event in &input.events {
                 match event {
                     Event::WindowFocused(false) => {
-                        frame.focus()
+                        request.get_or_insert(WindowRequest::Focus);
                     }
-                    Event::Text(text) => {
-                        self.selection = 0;
-                        self.text += text;
+                    Event::Text(text) | Event::Paste(text) => {
+                        self.edit_input(|input| input.insert(text));
                     }
                     Event::Key {
                         key,
                         pressed: true,
                         repeat: _,
-                        modifiers: Modifiers::NONE,
-                    } => match key {
-                        Key::Backspace => {
-                            self.selection = 0;
-                            self.text.pop();
-                        }
-                        Key::Escape => {
-                            let _ = self.sender.send(None);
-                            frame.close();
-                        },
-                        Key::Enter => {
-                            let _ = self.sender.send(self.selected_item_value());
-                            frame.close();
+                        modifiers,
+                    } => {
+                        if let Some(accept_key) = self.options.keymap.accept_key(*key, *modifiers) {
+                            request = Some(self.accept(Some(accept_key)));
+                        } else if let Some(action) = self.options.keymap.action(*key, *modifiers) {
+                            if let Some(action_request) = self.perform(action, pages) {
+                                request = Some(action_request);
+                            }
                         }
-                        Key::ArrowLeft => {
-                            let max = visible_items.len() - 1;
-                            self.selection = self
-                                .selection
-                                .checked_sub(1)
-                                .map_or(0, |i| clamp(0, i, max));
-                        }
-                        Key::ArrowRight => {
-                            let max = visible_items.len() - 1;
-                            self.selection = self
-                                .selection
-                                .checked_add(1)
-                                .map_or(max, |i| clamp(0, i, max));
-                        }
-                        _ => {}
-                    },
+                    }
                     _ => {}
                 }
             }
         });
+
+        request
     }
-}
 
-impl eframe::App for Selector {
-    fn update(&mut self, ctx: &Context, frame: &mut eframe::Frame) {
+    fn show_horizontal(&self, ui: &mut Ui, pages: &[Range<usize>]) {
+        let page = page_of(pages, self.selection);
+        let indicator_size = Vec2::new(Self::indicator_width(ui), ui.available_height());
+
+        ui.horizontal_centered(|ui| {
+            ui.horizontal_centered(|ui| {
+                ui.set_min_width(self.layout.input_min_width);
+                self.show_input(ui);
+            });
+
+            let previous = if page > 0 { "<" } else { "" };
+            ui.add_sized(indicator_size, Label::new(previous));
+
+            for index in pages.get(page).cloned().unwrap_or_default() {
+                ui.horizontal_centered(|ui| {
+                    let visible_item = &self.visible_items.items[index];
+                    let key = &self.items[visible_item.index].key;
+                    let colors = self.item_colors(index, visible_item);
+
+                    ui.add_space(ITEM_PADDING);
+                    ui.item_label(key, &visible_item.positions, colors);
+                    ui.add_space(ITEM_PADDING);
+                });
+            }
+
+            if page + 1 < pages.len() {
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    ui.add_sized(indicator_size, Label::new(">"));
+                });
+            }
+        });
+    }
+
+    fn show_vertical(&self, ui: &mut Ui, pages: &[Range<usize>]) {
+        let row_size = Vec2::new(ui.available_width(), self.layout.line_height);
+        let row_layout = Layout::left_to_right(Align::Center);
+
+        ui.allocate_ui_with_layout(row_size, row_layout, |ui| {
+            self.show_input(ui);
+        });
+
+        let page = page_of(pages, self.selection);
+        for index in pages.get(page).cloned().unwrap_or_default() {
+            ui.allocate_ui_with_layout(row_size, row_layout, |ui| {
+                let visible_item = &self.visible_items.items[index];
+                let key = &self.items[visible_item.index].key;
+                let colors = self.item_colors(index, visible_item);
+
+                ui.add_space(ITEM_PADDING);
+                ui.item_row(key, &visible_item.positions, colors);
+            });
+        }
+    }
+
+    /// Handles input and draws the selector, independently of the window showing it
+    pub fn show(&mut self, ctx: &Context) -> Option<WindowRequest> {
+        let mut request = self.receive_items();
+        if request.is_some() {
+            return request;
+        }
+
         let panel_frame = Frame {
             fill: ctx.style().visuals.panel_fill,
+            stroke: self
+                .colors
+                .border
+                .map_or(Stroke::NONE, |color| Stroke::new(1.0, color)),
             ..Default::default()
         };
         CentralPanel::default().frame(panel_frame).show(ctx, |ui| {
@@ -166,33 +673,210 @@ impl eframe::App for Selector {
             spacing.item_spacing = Vec2::ZERO;
             spacing.window_margin = Margin::same(0.0);
 
-            self.handle_input(ui, frame);
+            self.filter_items();
+            self.apply_preselect();
+            let mut pages = self.pages(ui);
+            request = self.handle_input(ui, &pages);
 
-            ui.horizontal_centered(|ui| {
-                ui.horizontal_centered(|ui| {
-                    ui.set_min_width(100.0);
-                    ui.label(format!("{} {}", self.prompt, self.text));
-                });
+            // Editing the input changes the visible items and the width left for them
+            if self.visible_items.query != self.input.text() {
+                self.filter_items();
+                pages = self.pages(ui);
+            }
 
-                let visible_items = self.visible_items();
-                for (index, item) in visible_items.iter().enumerate() {
-                    ui.horizontal_centered(|ui| {
-                        let text = RichText::new(&item.key);
-                        let (foreground_color, background_color) = if self.is_selection(index) {
-                            (
-                                Some(self.colors.foreground_selection),
-                                Some(self.colors.background_selection),
-                            )
-                        } else {
-                            (None, None)
-                        };
-
-                        ui.add_space(5.0);
-                        ui.item_label(text, foreground_color, background_color);
-                        ui.add_space(5.0);
-                    });
-                }
-            });
+            if self.layout.lines == 0 {
+                self.show_horizontal(ui, &pages);
+            } else {
+                self.show_vertical(ui, &pages);
+            }
         });
+
+        request
+    }
+}
+
+impl eframe::App for Selector {
+    fn update(&mut self, ctx: &Context, frame: &mut eframe::Frame) {
+        match self.show(ctx) {
+            Some(WindowRequest::Focus) => frame.focus(),
+            Some(WindowRequest::Close) => frame.close(),
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::item_filter::ItemFilters;
+    use crate::keymap::KeymapPreset;
+    use egui::{Key, Modifiers};
+
+    fn selector(lines: usize, query: &str, keys: &[&str]) -> Selector {
+        let (_, items_receiver) = mpsc::channel();
+        let (sender, _) = mpsc::channel();
+
+        let colors = AppColors {
+            foreground_normal: Color32::WHITE,
+            background_normal: Color32::BLACK,
+            foreground_selection: Color32::BLACK,
+            background_selection: Color32::WHITE,
+            foreground_match: Color32::RED,
+            foreground_marked: Color32::GREEN,
+            foreground_prompt: Color32::WHITE,
+            border: None,
+        };
+        let font = AppFont {
+            size: 13.0,
+            definitions: FontDefinitions::default(),
+        };
+        let layout = AppLayout {
+            input_min_width: 100.0,
+            lines,
+            line_height: 30.0,
+        };
+        let options = AppOptions {
+            multi: false,
+            keymap: Keymap::new(KeymapPreset::Default, &[], &[]),
+            query: query.to_string(),
+            preselect: None,
+            history: None,
+        };
+
+        let mut selector = Selector::new(
+            &Context::default(),
+            items_receiver,
+            ItemFilters::Substring.into(),
+            ">".to_string(),
+            colors,
+            font,
+            layout,
+            options,
+            sender,
+        );
+
+        selector.items = keys.iter().map(|key| item(key)).collect();
+
+        selector
+    }
+
+    fn item(key: &str) -> Item {
+        Item {
+            key: key.to_string(),
+            search: key.to_string(),
+            search_fields: Vec::new(),
+            value: key.to_string(),
+        }
+    }
+
+    fn visible_indices(selector: &Selector) -> Vec<usize> {
+        let visible_items = &selector.visible_items.items;
+        visible_items
+            .iter()
+            .map(|visible_item| visible_item.index)
+            .collect()
+    }
+
+    /// Performs the action, returning the selection and the cursor afterwards
+    fn perform(selector: &mut Selector, action: Action) -> (usize, usize) {
+        selector.filter_items();
+        let count = selector.visible_items.items.len();
+        let pages = paginate_lines(count, count);
+
+        selector.perform(action, &pages);
+
+        (selector.selection, selector.input.cursor())
+    }
+
+    #[test]
+    fn horizontal_keys_navigate_items_wherever_the_cursor_is() {
+        let mut selector = selector(0, "a", &["a", "ab", "abc"]);
+
+        // The cursor is at the end of the query
+        assert_eq!(perform(&mut selector, Action::Right), (1, 1));
+        assert_eq!(perform(&mut selector, Action::End), (2, 1));
+        assert_eq!(perform(&mut selector, Action::Left), (1, 1));
+        assert_eq!(perform(&mut selector, Action::Home), (0, 1));
+
+        // The cursor is at the start of the query and the first item is selected
+        selector.input.move_to_start();
+        assert_eq!(perform(&mut selector, Action::Left), (0, 0));
+        assert_eq!(perform(&mut selector, Action::Home), (0, 0));
+        assert_eq!(perform(&mut selector, Action::Right), (1, 0));
+        assert_eq!(perform(&mut selector, Action::End), (2, 0));
+    }
+
+    #[test]
+    fn horizontal_ctrl_keys_move_the_cursor() {
+        let mut selector = selector(0, "ab", &["ab", "abc"]);
+        let keymap = Keymap::new(KeymapPreset::Default, &[], &[]);
+        let action = |key| keymap.action(key, Modifiers::CTRL).unwrap();
+
+        assert_eq!(perform(&mut selector, action(Key::ArrowLeft)), (0, 1));
+        assert_eq!(perform(&mut selector, action(Key::Home)), (0, 0));
+        assert_eq!(perform(&mut selector, action(Key::ArrowRight)), (0, 1));
+        assert_eq!(perform(&mut selector, action(Key::End)), (0, 2));
+    }
+
+    #[test]
+    fn vertical_keys_move_the_cursor_whatever_is_selected() {
+        let mut selector = selector(3, "ab", &["ab", "abc"]);
+
+        assert_eq!(perform(&mut selector, Action::Left), (0, 1));
+        assert_eq!(perform(&mut selector, Action::Home), (0, 0));
+
+        selector.selection = 1;
+        assert_eq!(perform(&mut selector, Action::Right), (1, 1));
+        assert_eq!(perform(&mut selector, Action::End), (1, 2));
+        assert_eq!(perform(&mut selector, Action::Left), (1, 1));
+        assert_eq!(perform(&mut selector, Action::Home), (1, 0));
+    }
+
+    #[test]
+    fn items_read_later_are_merged_into_the_visible_items() {
+        let mut selector = selector(3, "ab", &["xab", "ab", "nope"]);
+        selector.item_filter = ItemFilters::Fuzzy.into();
+        selector.filter_items();
+        assert_eq!(visible_indices(&selector), [1, 0]);
+
+        selector
+            .items
+            .extend([item("ab"), item("a_b"), item("xab")]);
+        selector.filter_items();
+        assert_eq!(visible_indices(&selector), [1, 3, 4, 0, 5]);
+
+        // Filtering all items at once ranks them the same
+        selector.visible_items = VisibleItems::default();
+        selector.filter_items();
+        assert_eq!(visible_indices(&selector), [1, 3, 4, 0, 5]);
+    }
+
+    #[test]
+    fn visible_items_are_filtered_again_when_the_query_changes() {
+        let mut selector = selector(3, "ab", &["ab", "abc", "b"]);
+        selector.filter_items();
+        assert_eq!(visible_indices(&selector), [0, 1]);
+
+        selector.input.insert("c");
+        selector.filter_items();
+        assert_eq!(visible_indices(&selector), [1]);
+
+        selector.input = Input::default();
+        selector.filter_items();
+        assert_eq!(visible_indices(&selector), [0, 1, 2]);
+    }
+
+    #[test]
+    fn only_visible_items_are_measured() {
+        let mut selector = selector(0, "a", &["a", "b", "ab"]);
+        let ctx = Context::default();
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            selector.show(ctx);
+        });
+
+        let measured: Vec<bool> = selector.item_widths.iter().map(Option::is_some).collect();
+        assert_eq!(measured, [true, false, true]);
     }
 }
@@ -0,0 +1,199 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Context, Error, Result};
+use egui::Color32;
+use toml::Table;
+
+use crate::cli::parse_color;
+use crate::selector::AppColors;
+
+/// The themes compiled into the binary, selectable by name
+const BUILT_IN_THEMES: [(&str, &str); 3] = [
+    ("gruvbox", include_str!("../themes/gruvbox.toml")),
+    ("nord", include_str!("../themes/nord.toml")),
+    ("solarized", include_str!("../themes/solarized.toml")),
+];
+
+/// The colors of a theme, where missing colors are taken from elsewhere.
+/// In theme files the colors are given by the names of their command line arguments
+#[derive(Clone, Default)]
+pub struct Theme {
+    pub background_normal: Option<Color32>,
+    pub foreground_normal: Option<Color32>,
+    pub background_selection: Option<Color32>,
+    pub foreground_selection: Option<Color32>,
+    pub foreground_match: Option<Color32>,
+    pub foreground_marked: Option<Color32>,
+    pub foreground_prompt: Option<Color32>,
+    pub border_color: Option<Color32>,
+}
+
+impl Theme {
+    fn color_mut(&mut self, key: &str) -> Option<&mut Option<Color32>> {
+        let color = match key {
+            "background-normal" => &mut self.background_normal,
+            "foreground-normal" => &mut self.foreground_normal,
+            "background-selection" => &mut self.background_selection,
+            "foreground-selection" => &mut self.foreground_selection,
+            "foreground-match" => &mut self.foreground_match,
+            "foreground-marked" => &mut self.foreground_marked,
+            "foreground-prompt" => &mut self.foreground_prompt,
+            "border-color" => &mut self.border_color,
+            _ => return None,
+        };
+
+        Some(color)
+    }
+
+    /// Whether the key names a color, both in theme files and on the command line
+    pub fn is_color_key(key: &str) -> bool {
+        Self::default().color_mut(key).is_some()
+    }
+
+    fn from_toml(src: &str) -> Result<Self> {
+        let table = src.parse::<Table>()?;
+        let mut theme = Self::default();
+
+        for (key, value) in table {
+            let color = theme
+                .color_mut(&key)
+                .ok_or_else(|| anyhow!("unknown key `{key}`"))?;
+            let value = value
+                .as_str()
+                .ok_or_else(|| anyhow!("`{key}` should be a color string"))?;
+
+            *color =
+                Some(parse_color(value).with_context(|| format!("invalid color for `{key}`"))?);
+        }
+
+        Ok(theme)
+    }
+
+    /// Fills in the colors missing from this theme with those of another
+    #[must_use]
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            background_normal: self.background_normal.or(other.background_normal),
+            foreground_normal: self.foreground_normal.or(other.foreground_normal),
+            background_selection: self.background_selection.or(other.background_selection),
+            foreground_selection: self.foreground_selection.or(other.foreground_selection),
+            foreground_match: self.foreground_match.or(other.foreground_match),
+            foreground_marked: self.foreground_marked.or(other.foreground_marked),
+            foreground_prompt: self.foreground_prompt.or(other.foreground_prompt),
+            border_color: self.border_color.or(other.border_color),
+        }
+    }
+
+    /// The colors of the selector, using the default colors for those missing
+    pub fn app_colors(self) -> AppColors {
+        let foreground_normal = self
+            .foreground_normal
+            .unwrap_or(Color32::from_rgb(0xd0, 0xd0, 0xd0));
+
+        AppColors {
+            background_normal: self
+                .background_normal
+                .unwrap_or(Color32::from_rgb(0x3a, 0x3a, 0x3a)),
+            foreground_normal,
+            background_selection: self
+                .background_selection
+                .unwrap_or(Color32::from_rgb(0x85, 0xad, 0xd4)),
+            foreground_selection: self
+                .foreground_selection
+                .unwrap_or(Color32::from_rgb(0xd0, 0xd0, 0xd0)),
+            foreground_match: self
+                .foreground_match
+                .unwrap_or(Color32::from_rgb(0xf0, 0xc6, 0x74)),
+            foreground_marked: self
+                .foreground_marked
+                .unwrap_or(Color32::from_rgb(0xb5, 0xbd, 0x68)),
+            foreground_prompt: self.foreground_prompt.unwrap_or(foreground_normal),
+            border: self.border_color,
+        }
+    }
+}
+
+/// Parses a theme given either as the name of a built-in theme or as the path to a theme file
+pub fn parse_theme(src: &str) -> Result<Theme, Error> {
+    if let Some((_, theme)) = BUILT_IN_THEMES.iter().find(|(name, _)| *name == src) {
+        return Theme::from_toml(theme).with_context(|| format!("invalid built-in theme {src}"));
+    }
+
+    let path = Path::new(src);
+    if !path.exists() {
+        let names: Vec<&str> = BUILT_IN_THEMES.iter().map(|(name, _)| *name).collect();
+        return Err(anyhow!(
+            "failed to find a theme file at {src}, the built-in themes are {}",
+            names.join(", ")
+        ));
+    }
+
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read theme file {}", path.display()))?;
+
+    Theme::from_toml(&contents).with_context(|| format!("invalid theme file {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_themes_define_every_color() -> Result<()> {
+        for (name, _) in BUILT_IN_THEMES {
+            let theme = parse_theme(name)?;
+
+            assert!(theme.background_normal.is_some());
+            assert!(theme.foreground_normal.is_some());
+            assert!(theme.background_selection.is_some());
+            assert!(theme.foreground_selection.is_some());
+            assert!(theme.foreground_match.is_some());
+            assert!(theme.foreground_marked.is_some());
+            assert!(theme.foreground_prompt.is_some());
+            assert!(theme.border_color.is_some());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn theme_errors_point_to_the_key() {
+        let error = |src: &str| {
+            Theme::from_toml(src)
+                .map_err(|err| err.to_string())
+                .err()
+                .unwrap_or_default()
+        };
+
+        assert_eq!(error("border = \"fff\""), "unknown key `border`");
+        assert_eq!(
+            error("border-color = 1"),
+            "`border-color` should be a color string"
+        );
+        assert_eq!(
+            error("foreground-prompt = \"xyz\""),
+            "invalid color for `foreground-prompt`"
+        );
+    }
+
+    #[test]
+    fn or_keeps_colors_already_set() -> Result<()> {
+        let theme = Theme {
+            foreground_prompt: Some(Color32::RED),
+            ..Default::default()
+        }
+        .or(parse_theme("nord")?);
+
+        assert_eq!(theme.foreground_prompt, Some(Color32::RED));
+        assert_eq!(
+            theme.background_normal,
+            Some(Color32::from_rgb(0x2e, 0x34, 0x40))
+        );
+
+        let colors = Theme::default().app_colors();
+        assert_eq!(colors.foreground_prompt, colors.foreground_normal);
+        assert_eq!(colors.border, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,595 @@
+use std::{
+    ffi::CString,
+    num::NonZeroU32,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context as _, Result};
+use egui::{
+    epaint::{textures::TexturesDelta, ClippedPrimitive},
+    Context, Event, Key, Modifiers, Pos2, RawInput, Rect, Vec2,
+};
+use egui_glow::Painter;
+use glutin::{
+    config::ConfigTemplateBuilder,
+    context::{
+        ContextAttributesBuilder, NotCurrentGlContextSurfaceAccessor, PossiblyCurrentContext,
+    },
+    display::{Display, DisplayApiPreference},
+    prelude::{GlDisplay, GlSurface},
+    surface::{Surface, SurfaceAttributesBuilder, WindowSurface},
+};
+use raw_window_handle::{
+    RawDisplayHandle, RawWindowHandle, WaylandDisplayHandle, WaylandWindowHandle,
+};
+use smithay_client_toolkit::{
+    default_environment,
+    environment::{Environment, SimpleGlobal},
+    new_default_environment,
+    output::with_output_info,
+    reexports::{
+        calloop::{self, LoopHandle},
+        client::{
+            protocol::{wl_keyboard::WlKeyboard, wl_output::WlOutput, wl_surface::WlSurface},
+            Attached, Display as WlDisplay, Main,
+        },
+        protocols::wlr::unstable::layer_shell::v1::client::{
+            zwlr_layer_shell_v1::{Layer, ZwlrLayerShellV1},
+            zwlr_layer_surface_v1::{self, Anchor, KeyboardInteractivity, ZwlrLayerSurfaceV1},
+        },
+    },
+    seat::{
+        keyboard::{
+            keysyms, map_keyboard_repeat, Event as KeyboardEvent, KeyState, ModifiersState,
+            RepeatKind,
+        },
+        with_seat_data,
+    },
+    WaylandSource,
+};
+
+use crate::geometry::{Geometry, Length};
+use crate::screen::MonitorSelection;
+use crate::selector::{Selector, WindowRequest};
+
+default_environment!(Env,
+    fields = [
+        layer_shell: SimpleGlobal<ZwlrLayerShellV1>,
+    ],
+    singles = [
+        ZwlrLayerShellV1 => layer_shell
+    ],
+);
+
+const LETTERS: [Key; 26] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+];
+
+const DIGITS: [Key; 10] = [
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+];
+
+fn key_from_keysym(keysym: u32) -> Option<Key> {
+    let key = match keysym {
+        keysyms::XKB_KEY_Escape => Key::Escape,
+        keysyms::XKB_KEY_Return | keysyms::XKB_KEY_KP_Enter => Key::Enter,
+        keysyms::XKB_KEY_BackSpace => Key::Backspace,
+        keysyms::XKB_KEY_Delete => Key::Delete,
+        keysyms::XKB_KEY_Tab | keysyms::XKB_KEY_ISO_Left_Tab => Key::Tab,
+        keysyms::XKB_KEY_space => Key::Space,
+        keysyms::XKB_KEY_Left => Key::ArrowLeft,
+        keysyms::XKB_KEY_Right => Key::ArrowRight,
+        keysyms::XKB_KEY_Up => Key::ArrowUp,
+        keysyms::XKB_KEY_Down => Key::ArrowDown,
+        keysyms::XKB_KEY_Home => Key::Home,
+        keysyms::XKB_KEY_End => Key::End,
+        keysyms::XKB_KEY_Page_Up => Key::PageUp,
+        keysyms::XKB_KEY_Page_Down => Key::PageDown,
+        keysyms::XKB_KEY_a..=keysyms::XKB_KEY_z => LETTERS[(keysym - keysyms::XKB_KEY_a) as usize],
+        keysyms::XKB_KEY_A..=keysyms::XKB_KEY_Z => LETTERS[(keysym - keysyms::XKB_KEY_A) as usize],
+        keysyms::XKB_KEY_0..=keysyms::XKB_KEY_9 => DIGITS[(keysym - keysyms::XKB_KEY_0) as usize],
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+fn modifiers_from_state(state: ModifiersState) -> Modifiers {
+    Modifiers {
+        alt: state.alt,
+        ctrl: state.ctrl,
+        shift: state.shift,
+        mac_cmd: false,
+        command: state.ctrl,
+    }
+}
+
+/// Options for placing the layer surface on the output
+pub struct LayerShellOptions {
+    pub geometry: Geometry,
+    pub monitor: Option<MonitorSelection>,
+}
+
+/// The state shared between the wayland callbacks and the render loop
+#[derive(Default)]
+struct State {
+    events: Vec<Event>,
+    modifiers: Modifiers,
+    size: Option<(u32, u32)>,
+    scale: i32,
+    closed: bool,
+}
+
+impl State {
+    fn handle_keyboard_event(&mut self, event: KeyboardEvent<'_>) {
+        match event {
+            KeyboardEvent::Modifiers { modifiers } => {
+                self.modifiers = modifiers_from_state(modifiers);
+            }
+            KeyboardEvent::Key {
+                keysym,
+                state,
+                utf8,
+                ..
+            } => self.push_key(keysym, state == KeyState::Pressed, false, utf8),
+            KeyboardEvent::Repeat { keysym, utf8, .. } => self.push_key(keysym, true, true, utf8),
+            KeyboardEvent::Enter { .. } | KeyboardEvent::Leave { .. } => {}
+        }
+    }
+
+    fn push_key(&mut self, keysym: u32, pressed: bool, repeat: bool, utf8: Option<String>) {
+        if let Some(key) = key_from_keysym(keysym) {
+            self.events.push(Event::Key {
+                key,
+                pressed,
+                repeat,
+                modifiers: self.modifiers,
+            });
+        }
+
+        if pressed && !self.modifiers.ctrl && !self.modifiers.alt {
+            if let Some(text) = utf8.filter(|text| !text.chars().any(char::is_control)) {
+                self.events.push(Event::Text(text));
+            }
+        }
+    }
+}
+
+/// Paints egui output on a wayland surface through EGL
+struct Renderer {
+    surface: Surface<WindowSurface>,
+    context: PossiblyCurrentContext,
+    painter: Painter,
+    size: (NonZeroU32, NonZeroU32),
+}
+
+impl Renderer {
+    fn new(
+        display: &WlDisplay,
+        surface: &WlSurface,
+        size: (NonZeroU32, NonZeroU32),
+    ) -> Result<Self> {
+        let mut display_handle = WaylandDisplayHandle::empty();
+        display_handle.display = display.get_display_ptr().cast();
+        let mut window_handle = WaylandWindowHandle::empty();
+        window_handle.surface = surface.as_ref().c_ptr().cast();
+        let raw_window_handle = RawWindowHandle::Wayland(window_handle);
+
+        let gl_display = unsafe {
+            Display::new(
+                RawDisplayHandle::Wayland(display_handle),
+                DisplayApiPreference::Egl,
+            )
+        }
+        .context("failed to create egl display")?;
+
+        let config = unsafe { gl_display.find_configs(ConfigTemplateBuilder::new().build()) }
+            .context("failed to find gl configs")?
+            .next()
+            .context("failed to find a gl config")?;
+
+        let surface = unsafe {
+            gl_display.create_window_surface(
+                &config,
+                &SurfaceAttributesBuilder::<WindowSurface>::new().build(
+                    raw_window_handle,
+                    size.0,
+                    size.1,
+                ),
+            )
+        }
+        .context("failed to create gl surface")?;
+
+        let context = unsafe {
+            gl_display.create_context(
+                &config,
+                &ContextAttributesBuilder::new().build(Some(raw_window_handle)),
+            )
+        }
+        .context("failed to create gl context")?
+        .make_current(&surface)
+        .context("failed to make gl context current")?;
+
+        let gl = unsafe {
+            glow::Context::from_loader_function(|symbol| {
+                let symbol = CString::new(symbol).expect("gl symbols do not contain nul bytes");
+                gl_display.get_proc_address(&symbol)
+            })
+        };
+
+        let painter = Painter::new(Arc::new(gl), "", None)
+            .map_err(|err| anyhow!("failed to create painter: {err}"))?;
+
+        Ok(Self {
+            surface,
+            context,
+            painter,
+            size,
+        })
+    }
+
+    fn render(
+        &mut self,
+        size: (NonZeroU32, NonZeroU32),
+        pixels_per_point: f32,
+        clipped_primitives: &[ClippedPrimitive],
+        textures_delta: &TexturesDelta,
+    ) -> Result<()> {
+        if size != self.size {
+            self.size = size;
+            self.surface.resize(&self.context, size.0, size.1);
+        }
+
+        self.painter.paint_and_update_textures(
+            [size.0.get(), size.1.get()],
+            pixels_per_point,
+            clipped_primitives,
+            textures_delta,
+        );
+
+        self.surface
+            .swap_buffers(&self.context)
+            .context("failed to swap buffers")
+    }
+
+    fn destroy(&mut self) {
+        self.painter.destroy();
+    }
+}
+
+/// Finds the selected output, leaving the choice to the compositor when selecting
+/// the focused output or the one under the pointer
+fn find_output(
+    env: &Environment<Env>,
+    selection: Option<&MonitorSelection>,
+) -> Result<Option<WlOutput>> {
+    let outputs = env.get_all_outputs();
+
+    let output = match selection {
+        Some(MonitorSelection::Index(index)) => outputs
+            .into_iter()
+            .nth(*index)
+            .with_context(|| format!("failed to find an output with index {index}"))?,
+        Some(MonitorSelection::Name(name)) => outputs
+            .into_iter()
+            .find(|output| with_output_info(output, |info| &info.name == name).unwrap_or(false))
+            .with_context(|| format!("failed to find an output named {name}"))?,
+        _ => return Ok(None),
+    };
+
+    Ok(Some(output))
+}
+
+/// The logical width of the output, or of the first output when the compositor chooses it
+fn output_width(env: &Environment<Env>, output: Option<&WlOutput>) -> Option<f32> {
+    let output = output
+        .cloned()
+        .or_else(|| env.get_all_outputs().into_iter().next())?;
+
+    with_output_info(&output, |info| {
+        let mode = info.modes.iter().find(|mode| mode.is_current)?;
+
+        #[allow(clippy::cast_precision_loss)]
+        Some(mode.dimensions.0 as f32 / info.scale_factor.max(1) as f32)
+    })
+    .flatten()
+}
+
+/// Anchors the layer surface to the edges the bar is placed at, leaving the compositor to
+/// center it along the axes it is not anchored on. Margins are only used for the offsets
+fn place_layer_surface(
+    layer_surface: &ZwlrLayerSurfaceV1,
+    geometry: &Geometry,
+    output_width: Option<f32>,
+) {
+    let width = match geometry.width {
+        Some(Length::Percent(_)) => geometry
+            .width
+            .zip(output_width)
+            .map(|(width, output_width)| width.resolve(output_width)),
+        width => width.map(|width| width.resolve(0.0)),
+    };
+
+    let vertical = match geometry.y {
+        Some(_) if geometry.bottom => Anchor::Bottom,
+        Some(_) => Anchor::Top,
+        None if geometry.center => Anchor::empty(),
+        None if geometry.bottom => Anchor::Bottom,
+        None => Anchor::Top,
+    };
+    let horizontal = match (width, geometry.x) {
+        (None, _) => Anchor::Left | Anchor::Right,
+        (Some(_), Some(_)) => Anchor::Left,
+        (Some(_), None) if geometry.center => Anchor::empty(),
+        (Some(_), None) => Anchor::Left,
+    };
+
+    #[allow(clippy::cast_possible_truncation)]
+    let offset = |offset: Option<f32>| offset.unwrap_or_default() as i32;
+    let (top, bottom) = if geometry.bottom {
+        (0, offset(geometry.y))
+    } else {
+        (offset(geometry.y), 0)
+    };
+
+    // A zero size is only allowed along axes anchored to both edges
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let size = (
+        width.map_or(0, |width| width.max(1.0) as u32),
+        geometry.height.max(1.0) as u32,
+    );
+
+    layer_surface.set_anchor(vertical | horizontal);
+    layer_surface.set_margin(top, 0, bottom, offset(geometry.x));
+    layer_surface.set_size(size.0, size.1);
+}
+
+/// Creates a layer surface placed according to the geometry
+fn create_layer_surface(
+    layer_shell: &Attached<ZwlrLayerShellV1>,
+    surface: &WlSurface,
+    output: Option<&WlOutput>,
+    geometry: &Geometry,
+    output_width: Option<f32>,
+) -> Main<ZwlrLayerSurfaceV1> {
+    let layer_surface =
+        layer_shell.get_layer_surface(surface, output, Layer::Overlay, "rmenu".to_string());
+
+    place_layer_surface(&layer_surface, geometry, output_width);
+    layer_surface.set_keyboard_interactivity(KeyboardInteractivity::Exclusive);
+
+    layer_surface.quick_assign(|layer_surface, event, mut data| {
+        let Some(state) = data.get::<State>() else {
+            return;
+        };
+
+        match event {
+            zwlr_layer_surface_v1::Event::Configure {
+                serial,
+                width,
+                height,
+            } => {
+                layer_surface.ack_configure(serial);
+                state.size = Some((width, height));
+            }
+            zwlr_layer_surface_v1::Event::Closed => state.closed = true,
+            _ => {}
+        }
+    });
+
+    layer_surface
+}
+
+/// Maps the keyboards of all seats, feeding their events into the state
+fn map_keyboards(
+    env: &Environment<Env>,
+    loop_handle: &LoopHandle<'static, State>,
+) -> Result<Vec<WlKeyboard>> {
+    env.get_all_seats()
+        .iter()
+        .filter(|seat| {
+            with_seat_data(seat, |data| data.has_keyboard && !data.defunct).unwrap_or(false)
+        })
+        .map(|seat| {
+            map_keyboard_repeat(
+                loop_handle.clone(),
+                seat,
+                None,
+                RepeatKind::System,
+                |event, _, mut data| {
+                    if let Some(state) = data.get::<State>() {
+                        state.handle_keyboard_event(event);
+                    }
+                },
+            )
+            .map_err(|err| anyhow!("failed to map keyboard: {err:?}"))
+        })
+        .collect()
+}
+
+/// Shows the selector as a wlr-layer-shell overlay placed according to the geometry,
+/// with exclusive keyboard focus, until the selector requests to close
+pub fn run_layer_shell(
+    options: &LayerShellOptions,
+    app_creator: impl FnOnce(&Context) -> Selector,
+) -> Result<()> {
+    let (env, display, queue) =
+        new_default_environment!(Env, fields = [layer_shell: SimpleGlobal::new(),])
+            .context("failed to connect to the wayland compositor")?;
+
+    let layer_shell = env
+        .get_global::<ZwlrLayerShellV1>()
+        .context("the compositor does not support wlr-layer-shell")?;
+
+    let mut event_loop =
+        calloop::EventLoop::<State>::try_new().context("failed to create event loop")?;
+
+    let surface = env
+        .create_surface_with_scale_callback(|scale, surface, mut data| {
+            surface.set_buffer_scale(scale);
+            if let Some(state) = data.get::<State>() {
+                state.scale = scale;
+            }
+        })
+        .detach();
+
+    let output = find_output(&env, options.monitor.as_ref())?;
+    let layer_surface = create_layer_surface(
+        &layer_shell,
+        &surface,
+        output.as_ref(),
+        &options.geometry,
+        output_width(&env, output.as_ref()),
+    );
+    let keyboards = map_keyboards(&env, &event_loop.handle())?;
+
+    // Commit so that the compositor sends the initial configure
+    surface.commit();
+
+    WaylandSource::new(queue)
+        .quick_insert(event_loop.handle())
+        .map_err(|err| anyhow!("failed to insert wayland source: {err}"))?;
+
+    let mut state = State {
+        scale: 1,
+        ..Default::default()
+    };
+
+    wait_for_configure(&display, &mut event_loop, &mut state)?;
+
+    let mut renderer = Renderer::new(&display, &surface, physical_size(&state))?;
+
+    // Repaints requested from other threads, like the one reading items, wake the loop.
+    // Later repaints are covered by the timeout of the dispatch
+    let (ping, ping_source) = calloop::ping::make_ping().context("failed to create ping source")?;
+    event_loop
+        .handle()
+        .insert_source(ping_source, |(), &mut (), _| {})
+        .map_err(|err| anyhow!("failed to insert ping source: {err}"))?;
+
+    let ctx = Context::default();
+    ctx.set_request_repaint_callback(move |info| {
+        if info.after.is_zero() {
+            ping.ping();
+        }
+    });
+    let mut selector = app_creator(&ctx);
+    let start = Instant::now();
+
+    while !state.closed {
+        #[allow(clippy::cast_precision_loss)]
+        let logical_size = state.size.map_or(Vec2::ZERO, |(width, height)| {
+            Vec2::new(width as f32, height as f32)
+        });
+
+        #[allow(clippy::cast_precision_loss)]
+        let raw_input = RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, logical_size)),
+            pixels_per_point: Some(state.scale as f32),
+            time: Some(start.elapsed().as_secs_f64()),
+            modifiers: state.modifiers,
+            events: std::mem::take(&mut state.events),
+            ..Default::default()
+        };
+
+        let mut request = None;
+        let output = ctx.run(raw_input, |ctx| request = selector.show(ctx));
+
+        let clipped_primitives = ctx.tessellate(output.shapes);
+        #[allow(clippy::cast_precision_loss)]
+        renderer.render(
+            physical_size(&state),
+            state.scale as f32,
+            &clipped_primitives,
+            &output.textures_delta,
+        )?;
+
+        if let Some(WindowRequest::Close) = request {
+            break;
+        }
+
+        let timeout = (output.repaint_after != Duration::MAX).then_some(output.repaint_after);
+
+        display
+            .flush()
+            .context("failed to flush wayland connection")?;
+        event_loop
+            .dispatch(timeout, &mut state)
+            .context("failed to dispatch wayland events")?;
+    }
+
+    renderer.destroy();
+
+    for keyboard in keyboards {
+        keyboard.release();
+    }
+    layer_surface.destroy();
+    surface.destroy();
+    display
+        .flush()
+        .context("failed to flush wayland connection")?;
+
+    Ok(())
+}
+
+fn wait_for_configure(
+    display: &WlDisplay,
+    event_loop: &mut calloop::EventLoop<State>,
+    state: &mut State,
+) -> Result<()> {
+    while state.size.is_none() && !state.closed {
+        display
+            .flush()
+            .context("failed to flush wayland connection")?;
+        event_loop
+            .dispatch(None, state)
+            .context("failed to dispatch wayland events")?;
+    }
+
+    Ok(())
+}
+
+fn physical_size(state: &State) -> (NonZeroU32, NonZeroU32) {
+    let (width, height) = state.size.unwrap_or_default();
+    let scale = state.scale.unsigned_abs().max(1);
+
+    (
+        NonZeroU32::new(width * scale).unwrap_or(NonZeroU32::MIN),
+        NonZeroU32::new(height * scale).unwrap_or(NonZeroU32::MIN),
+    )
+}
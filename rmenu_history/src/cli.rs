@@ -1,5 +1,6 @@
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use clap::{ArgGroup, Parser, Subcommand};
+use rmenu_history::DEFAULT_HALF_LIFE;
+use std::{path::PathBuf, time::Duration};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -7,6 +8,10 @@ pub struct Cli {
     /// The path of the history file to sort by
     pub path: PathBuf,
 
+    /// The time after which the score of an entry has halved, like `2weeks` or `3d`
+    #[arg(long, value_parser = humantime::parse_duration, default_value = DEFAULT_HALF_LIFE)]
+    pub half_life: Duration,
+
     #[command(subcommand)]
     pub cmd: Commands,
 }
@@ -23,4 +28,35 @@ pub enum Commands {
         /// The new entry to add to the history file
         entry: String,
     },
+    /// Remove an entry from the history file
+    Remove {
+        /// The entry to remove from the history file
+        entry: String,
+    },
+    /// Remove the entries matching any of the given criteria from the history file
+    #[command(group(
+        ArgGroup::new("criteria")
+            .args(["max_entries", "older_than", "missing_from"])
+            .required(true)
+            .multiple(true)
+    ))]
+    Prune {
+        /// Keep only this many entries, those with the highest scores
+        #[arg(long)]
+        max_entries: Option<usize>,
+        /// Remove entries not selected within this long, like `90days`
+        #[arg(long, value_parser = humantime::parse_duration)]
+        older_than: Option<Duration>,
+        /// Remove entries not listed in this file of items, `-` to read them from stdin
+        #[arg(long)]
+        missing_from: Option<PathBuf>,
+    },
+    /// List the entries of the history file with their scores, highest first
+    List,
+    /// Show the number of entries and selections in the history file
+    Stats {
+        /// The number of most selected entries to show
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+    },
 }
@@ -0,0 +1,747 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::{hash_map::RandomState, HashMap, HashSet},
+    ffi::OsString,
+    fmt::{self, Display, Write},
+    fs::{File, OpenOptions},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tempfile::NamedTempFile;
+
+/// The version of the history file format written
+const HISTORY_VERSION: u32 = 1;
+
+/// The half-life used when none is given, in the format of `humantime`
+pub const DEFAULT_HALF_LIFE: &str = "14days";
+
+/// A problem found in a history file which did not stop it from being used
+#[derive(Debug)]
+pub struct HistoryWarning {
+    path: PathBuf,
+    message: String,
+}
+
+impl Display for HistoryWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Warning: {}: {}",
+            self.path.to_string_lossy(),
+            self.message
+        )
+    }
+}
+
+/// The first line of a history file, telling the format of the records following it
+#[derive(Serialize, Deserialize)]
+struct HistoryHeader {
+    format: String,
+    version: u32,
+}
+
+impl HistoryHeader {
+    const FORMAT: &'static str = "rmenu_history";
+}
+
+/// How often and how recently an entry was selected
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryItem {
+    /// The number of times the entry was selected
+    pub count: i32,
+    /// When the entry was last selected, in seconds since the Unix epoch
+    pub last_used: u64,
+    /// The frecency score at the time the entry was last selected
+    pub score: f64,
+    /// Any other data stored for the entry, kept as it is
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub metadata: Map<String, Value>,
+}
+
+/// A line of the history file following the header
+#[derive(Serialize, Deserialize)]
+struct HistoryRecord {
+    entry: String,
+    #[serde(flatten)]
+    item: HistoryItem,
+}
+
+impl HistoryItem {
+    /// The score decayed by the time passed since the entry was last selected
+    #[allow(clippy::cast_precision_loss)]
+    pub fn frecency(&self, now: u64, half_life: Duration) -> f64 {
+        let elapsed = now.saturating_sub(self.last_used) as f64;
+
+        self.score * 0.5_f64.powf(elapsed / half_life.as_secs_f64().max(1.0))
+    }
+
+    /// The item after selecting the entry once more
+    fn selected(self, now: u64, half_life: Duration) -> Self {
+        Self {
+            count: self.count + 1,
+            last_used: now,
+            score: self.frecency(now, half_life) + 1.0,
+            metadata: self.metadata,
+        }
+    }
+}
+
+pub type HistoryItems = HashMap<String, HistoryItem, RandomState>;
+
+pub fn now() -> Result<u64> {
+    Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("system time is before the Unix epoch.")?
+        .as_secs())
+}
+
+/// Parses a `count:entry` line of an unversioned history file,
+/// treating the entry as last selected `now` with its count as score
+fn parse_legacy_line(line: &str, now: u64) -> Result<(String, HistoryItem)> {
+    match line.split_once(':') {
+        Some((n, a)) => match n.parse::<i32>() {
+            Ok(count) => {
+                let item = HistoryItem {
+                    count,
+                    last_used: now,
+                    score: f64::from(count),
+                    metadata: Map::new(),
+                };
+                Ok((a.to_string(), item))
+            }
+            Err(_) => Err(anyhow!("could not parse `{}` as an integer.", n)),
+        },
+        None => Err(anyhow!("could not split `{}`.", line)),
+    }
+}
+
+fn parse_record_line(line: &str) -> Result<(String, HistoryItem)> {
+    let record: HistoryRecord = serde_json::from_str(line)?;
+
+    Ok((record.entry, record.item))
+}
+
+/// Parses the content of a history file, skipping the lines which cannot be parsed.
+/// Files without a header are read as unversioned files and rewritten as versioned ones
+/// on the next write. Returns the items together with a warning for each skipped line
+fn parse_history(content: &str, now: u64) -> Result<(HistoryItems, Vec<String>)> {
+    let mut lines = content.lines().enumerate().peekable();
+
+    let header = lines
+        .peek()
+        .and_then(|(_, line)| serde_json::from_str::<HistoryHeader>(line).ok())
+        .filter(|header| header.format == HistoryHeader::FORMAT);
+
+    let parse_line: fn(&str, u64) -> Result<(String, HistoryItem)> = match header {
+        Some(header) if header.version > HISTORY_VERSION => {
+            return Err(anyhow!(
+                "history file version {} is newer than the supported version {HISTORY_VERSION}.",
+                header.version
+            ));
+        }
+        Some(_) => {
+            lines.next();
+            |line, _| parse_record_line(line)
+        }
+        None => parse_legacy_line,
+    };
+
+    let mut history_items = HistoryItems::new();
+    let mut warnings = Vec::new();
+
+    for (index, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match parse_line(line, now) {
+            Ok((entry, item)) => {
+                history_items.insert(entry, item);
+            }
+            Err(err) => warnings.push(format!("skipping line {}: {err:#}", index + 1)),
+        }
+    }
+
+    Ok((history_items, warnings))
+}
+
+/// Reads the history file, where a missing file is an empty history.
+/// Returns the items together with a warning for each skipped line
+pub fn parse_history_file(path: &PathBuf, now: u64) -> Result<(HistoryItems, Vec<HistoryWarning>)> {
+    let content = match std::fs::read_to_string(path) {
+        Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+        content => {
+            content.with_context(|| format!("could not read file `{}`.", path.to_string_lossy()))?
+        }
+    };
+
+    let (history_items, warnings) = parse_history(&content, now)
+        .with_context(|| format!("could not parse file `{}`.", path.to_string_lossy()))?;
+
+    let warnings = warnings
+        .into_iter()
+        .map(|message| HistoryWarning {
+            path: path.clone(),
+            message,
+        })
+        .collect();
+
+    Ok((history_items, warnings))
+}
+
+fn format_history(history_items: &HistoryItems) -> Result<String> {
+    let mut content = String::new();
+
+    let header = HistoryHeader {
+        format: HistoryHeader::FORMAT.to_string(),
+        version: HISTORY_VERSION,
+    };
+    writeln!(&mut content, "{}", serde_json::to_string(&header)?)
+        .context("could not format history header.")?;
+
+    let mut sorted_items = history_items
+        .iter()
+        .map(|(a, item)| (item, a))
+        .collect::<Vec<(&HistoryItem, &String)>>();
+
+    sorted_items.sort_by_key(|(item, _)| item.last_used);
+
+    for (item, a) in sorted_items {
+        let record = HistoryRecord {
+            entry: a.clone(),
+            item: item.clone(),
+        };
+        let line = serde_json::to_string(&record)
+            .with_context(|| format!("could not format values of `{a}`."))?;
+        writeln!(&mut content, "{line}")
+            .with_context(|| format!("could not format values of `{a}`."))?;
+    }
+
+    Ok(content)
+}
+
+/// The path of the file the history file links to, or the path itself if it does not exist yet
+fn resolve_history_file(path: &PathBuf) -> Result<PathBuf> {
+    match std::fs::canonicalize(path) {
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(path.clone()),
+        resolved => resolved
+            .with_context(|| format!("could not resolve path `{}`.", path.to_string_lossy())),
+    }
+}
+
+/// Writes the history to a temporary file next to the history file and renames it over
+/// the history file, so the history file is never left partially written. Links are
+/// followed and the permissions of the history file are kept
+fn write_history_file(path: &PathBuf, history_items: &HistoryItems) -> Result<()> {
+    let content = format_history(history_items)?;
+    let path = &resolve_history_file(path)?;
+
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut file = NamedTempFile::new_in(dir)
+        .with_context(|| format!("failed to create a temporary file in `{}`.", dir.display()))?;
+
+    io::Write::write_all(&mut file, content.as_bytes())
+        .and_then(|()| file.as_file().sync_all())
+        .with_context(|| format!("failed to write content to `{}`.", file.path().display()))?;
+
+    if let Ok(metadata) = std::fs::metadata(path) {
+        file.as_file()
+            .set_permissions(metadata.permissions())
+            .with_context(|| {
+                format!("failed to set permissions of `{}`.", file.path().display())
+            })?;
+    }
+
+    file.persist(path)
+        .with_context(|| format!("failed to write content to `{}`.", path.to_string_lossy()))?;
+
+    Ok(())
+}
+
+/// Takes an exclusive advisory lock on the lock file next to the history file,
+/// released when the returned file is dropped. The history file itself is not locked
+/// as it is replaced on every write
+fn lock_history_file(path: &Path) -> Result<File> {
+    let mut lock_path = OsString::from(path.as_os_str());
+    lock_path.push(".lock");
+    let lock_path = PathBuf::from(lock_path);
+
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("failed to open lock file `{}`.", lock_path.display()))?;
+
+    lock_file
+        .lock()
+        .with_context(|| format!("failed to lock `{}`.", lock_path.display()))?;
+
+    Ok(lock_file)
+}
+
+/// Sorts the items by their scores, highest first, and alphabetically among equal scores
+pub fn sort(history_items: &HistoryItems, items: &mut [String], now: u64, half_life: Duration) {
+    let frecency = |a: &String| {
+        history_items
+            .get(a)
+            .map_or(0.0, |item| item.frecency(now, half_life))
+    };
+
+    items.sort_by(|a, b| {
+        frecency(b)
+            .total_cmp(&frecency(a))
+            .then_with(|| a.to_ascii_lowercase().cmp(&b.to_ascii_lowercase()))
+    });
+}
+
+/// The entries with their scores, highest first
+pub fn ranked(
+    history_items: &HistoryItems,
+    now: u64,
+    half_life: Duration,
+) -> Vec<(&String, &HistoryItem, f64)> {
+    let mut ranked_items = history_items
+        .iter()
+        .map(|(a, item)| (a, item, item.frecency(now, half_life)))
+        .collect::<Vec<_>>();
+
+    ranked_items.sort_by(|(a, _, x), (b, _, y)| {
+        y.total_cmp(x)
+            .then_with(|| a.to_ascii_lowercase().cmp(&b.to_ascii_lowercase()))
+    });
+
+    ranked_items
+}
+
+/// Copies a history file with skipped lines to a backup before it is rewritten without them,
+/// refusing to rewrite it when none of its lines could be parsed
+fn keep_backup(
+    path: &PathBuf,
+    history_items: &HistoryItems,
+    warnings: &[HistoryWarning],
+) -> Result<PathBuf> {
+    if history_items.is_empty() {
+        let messages: Vec<&str> = warnings
+            .iter()
+            .map(|warning| warning.message.as_str())
+            .collect();
+
+        return Err(anyhow!(
+            "could not parse any entry of `{}`, refusing to overwrite it: {}",
+            path.to_string_lossy(),
+            messages.join(", ")
+        ));
+    }
+
+    let mut backup_path = OsString::from(path.as_os_str());
+    backup_path.push(".bak");
+    let backup_path = PathBuf::from(backup_path);
+
+    std::fs::copy(path, &backup_path).with_context(|| {
+        format!(
+            "failed to back up history file to `{}`.",
+            backup_path.display()
+        )
+    })?;
+
+    Ok(backup_path)
+}
+
+/// Reads, changes and writes the history file while holding its lock.
+/// Returns the result of the change together with the warnings from reading the file
+pub fn modify_history_file<T>(
+    path: &PathBuf,
+    now: u64,
+    modify: impl FnOnce(&mut HistoryItems) -> Result<T>,
+) -> Result<(T, Vec<HistoryWarning>)> {
+    let path = &resolve_history_file(path)?;
+    let _lock = lock_history_file(path)?;
+    let (mut history_items, mut warnings) = parse_history_file(path, now)?;
+
+    if !warnings.is_empty() {
+        let backup_path = keep_backup(path, &history_items, &warnings)?;
+        warnings.push(HistoryWarning {
+            path: path.clone(),
+            message: format!(
+                "kept a copy of the skipped lines in `{}`.",
+                backup_path.display()
+            ),
+        });
+    }
+
+    let result = modify(&mut history_items)?;
+
+    write_history_file(path, &history_items)?;
+
+    Ok((result, warnings))
+}
+
+/// Records a selection of the entry
+pub fn select(history_items: &mut HistoryItems, entry: String, now: u64, half_life: Duration) {
+    let item = match history_items.remove(&entry) {
+        Some(item) => item.selected(now, half_life),
+        None => HistoryItem {
+            count: 1,
+            last_used: now,
+            score: 1.0,
+            metadata: Map::new(),
+        },
+    };
+    history_items.insert(entry, item);
+}
+
+/// Records a selection of the entry in the history file, returning the warnings from reading it
+pub fn update(
+    path: &PathBuf,
+    entry: String,
+    now: u64,
+    half_life: Duration,
+) -> Result<Vec<HistoryWarning>> {
+    let ((), warnings) = modify_history_file(path, now, |history_items| {
+        select(history_items, entry, now, half_life);
+
+        Ok(())
+    })?;
+
+    Ok(warnings)
+}
+
+/// Removes the entry from the history file, returning the warnings from reading it
+pub fn remove(path: &PathBuf, entry: &str, now: u64) -> Result<Vec<HistoryWarning>> {
+    let ((), warnings) = modify_history_file(path, now, |history_items| {
+        history_items
+            .remove(entry)
+            .map(|_| ())
+            .ok_or_else(|| anyhow!("could not find entry `{entry}`."))
+    })?;
+
+    Ok(warnings)
+}
+
+/// What makes `prune` remove an entry
+pub struct PruneCriteria {
+    pub max_entries: Option<usize>,
+    pub older_than: Option<Duration>,
+    /// The items to keep the entries of, all entries are kept if `None`
+    pub listed: Option<HashSet<String>>,
+}
+
+/// Removes the entries matching any of the criteria, returning the number of entries removed
+pub fn prune(
+    history_items: &mut HistoryItems,
+    criteria: &PruneCriteria,
+    now: u64,
+    half_life: Duration,
+) -> usize {
+    let len = history_items.len();
+
+    if let Some(older_than) = criteria.older_than {
+        let cutoff = now.saturating_sub(older_than.as_secs());
+        history_items.retain(|_, item| item.last_used >= cutoff);
+    }
+
+    if let Some(listed) = &criteria.listed {
+        history_items.retain(|a, _| listed.contains(a));
+    }
+
+    if let Some(max_entries) = criteria.max_entries {
+        let removed = ranked(history_items, now, half_life)
+            .into_iter()
+            .skip(max_entries)
+            .map(|(a, _, _)| a.clone())
+            .collect::<Vec<String>>();
+
+        for a in removed {
+            history_items.remove(&a);
+        }
+    }
+
+    len - history_items.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! vec_of_strings {
+        ($($x:expr),*) => (vec![$($x.to_string()),*]);
+    }
+
+    const DAY: u64 = 24 * 60 * 60;
+    const HALF_LIFE: Duration = Duration::from_secs(14 * DAY);
+    const NOW: u64 = 1000 * DAY;
+
+    fn history_item(count: i32, days_ago: u64) -> HistoryItem {
+        HistoryItem {
+            count,
+            last_used: NOW - days_ago * DAY,
+            score: f64::from(count),
+            metadata: Map::new(),
+        }
+    }
+
+    #[test]
+    fn sort_sorts_alphabetically() -> Result<()> {
+        let history_items = HistoryItems::new();
+        let mut items = vec_of_strings!["Insync", "Firefox", "Spotify"];
+        let sorted_items = vec_of_strings!["Firefox", "Insync", "Spotify"];
+
+        sort(&history_items, &mut items, NOW, HALF_LIFE);
+
+        assert_eq!(items, sorted_items);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sort_sorts_historical_items_first() -> Result<()> {
+        let mut history_items = HistoryItems::new();
+
+        history_items.insert("Insync".to_string(), history_item(1, 0));
+        history_items.insert("Spotify".to_string(), history_item(2, 0));
+
+        let mut items = vec_of_strings!["Insync", "Firefox", "Spotify"];
+        let sorted_items = vec_of_strings!["Spotify", "Insync", "Firefox"];
+
+        sort(&history_items, &mut items, NOW, HALF_LIFE);
+
+        assert_eq!(items, sorted_items);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sort_sorts_recent_items_before_old_frequent_items() -> Result<()> {
+        let mut history_items = HistoryItems::new();
+
+        history_items.insert("Insync".to_string(), history_item(200, 365));
+        history_items.insert("Spotify".to_string(), history_item(3, 1));
+
+        let mut items = vec_of_strings!["Insync", "Firefox", "Spotify"];
+        let sorted_items = vec_of_strings!["Spotify", "Insync", "Firefox"];
+
+        sort(&history_items, &mut items, NOW, HALF_LIFE);
+
+        assert_eq!(items, sorted_items);
+
+        Ok(())
+    }
+
+    #[test]
+    fn selected_decays_the_previous_score() {
+        let item = history_item(4, 14).selected(NOW, HALF_LIFE);
+
+        assert_eq!(item.count, 5);
+        assert_eq!(item.last_used, NOW);
+        assert!((item.score - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn parse_legacy_line_keeps_colons_in_entries() -> Result<()> {
+        assert_eq!(
+            parse_legacy_line("3:10:30:standup", NOW)?,
+            ("10:30:standup".to_string(), history_item(3, 0))
+        );
+        assert_eq!(
+            parse_legacy_line("3:a:b", NOW)?,
+            ("a:b".to_string(), history_item(3, 0))
+        );
+        assert!(parse_legacy_line("a:b", NOW).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_history_migrates_unversioned_files() -> Result<()> {
+        let (history_items, warnings) = parse_history("2:Spotify\n1:Insync\n", NOW)?;
+        assert!(warnings.is_empty());
+
+        let content = format_history(&history_items)?;
+        assert!(content.starts_with(r#"{"format":"rmenu_history","version":1}"#));
+
+        let (migrated_items, warnings) = parse_history(&content, NOW)?;
+        assert!(warnings.is_empty());
+        assert_eq!(migrated_items, history_items);
+        assert_eq!(migrated_items["Spotify"], history_item(2, 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_history_skips_corrupted_lines() -> Result<()> {
+        let content = [
+            r#"{"format":"rmenu_history","version":1}"#,
+            r#"{"entry":"Spotify","count":2,"last_used":86400,"score":2.0,"metadata":{"a":1}}"#,
+            r#"{"entry":"Insync","count":"#,
+            r#"{"entry":"Firefox","count":1,"last_used":86400,"score":1.0}"#,
+        ]
+        .join("\n");
+
+        let (history_items, warnings) = parse_history(&content, NOW)?;
+
+        assert_eq!(history_items.len(), 2);
+        assert_eq!(history_items["Spotify"].metadata["a"], 1);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("skipping line 3:"));
+
+        let newer = r#"{"format":"rmenu_history","version":2}"#;
+        assert!(parse_history(newer, NOW).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_does_not_lose_concurrent_updates() -> Result<()> {
+        const THREADS: i32 = 8;
+        const UPDATES: i32 = 10;
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("history");
+        std::fs::write(&path, "")?;
+
+        let handles: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || -> Result<()> {
+                    for _ in 0..UPDATES {
+                        update(&path, "Firefox".to_string(), NOW, HALF_LIFE)?;
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle
+                .join()
+                .map_err(|_| anyhow!("update thread panicked."))??;
+        }
+
+        let (history_items, _) = parse_history_file(&path, NOW)?;
+        assert_eq!(history_items["Firefox"].count, THREADS * UPDATES);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn update_keeps_links_and_permissions() -> Result<()> {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = tempfile::tempdir()?;
+        let target = dir.path().join("target");
+        let path = dir.path().join("history");
+        std::fs::write(&target, "")?;
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o644))?;
+        symlink(&target, &path)?;
+
+        update(&path, "Firefox".to_string(), NOW, HALF_LIFE)?;
+
+        assert!(std::fs::symlink_metadata(&path)?.file_type().is_symlink());
+        assert_eq!(
+            std::fs::metadata(&target)?.permissions().mode() & 0o777,
+            0o644
+        );
+        assert_eq!(parse_history_file(&target, NOW)?.0["Firefox"].count, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_keeps_files_with_skipped_lines() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("history");
+
+        let unknown_header = [
+            r#"{"format":"other","version":1}"#,
+            r#"{"entry":"Spotify","count":2,"last_used":86400,"score":2.0}"#,
+        ]
+        .join("\n");
+        std::fs::write(&path, &unknown_header)?;
+
+        assert!(update(&path, "Firefox".to_string(), NOW, HALF_LIFE).is_err());
+        assert_eq!(std::fs::read_to_string(&path)?, unknown_header);
+
+        let corrupted = "2:Spotify\nInsync\n";
+        std::fs::write(&path, corrupted)?;
+
+        let warnings = update(&path, "Firefox".to_string(), NOW, HALF_LIFE)?;
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[1]
+            .to_string()
+            .starts_with(&format!("Warning: {}: kept a copy", path.to_string_lossy())));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("history.bak"))?,
+            corrupted
+        );
+
+        let (history_items, warnings) = parse_history_file(&path, NOW)?;
+        assert_eq!(history_items.len(), 2);
+        assert!(warnings.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn prune_removes_entries_matching_any_criteria() {
+        let mut history_items = HistoryItems::new();
+
+        history_items.insert("Insync".to_string(), history_item(200, 365));
+        history_items.insert("Spotify".to_string(), history_item(3, 1));
+        history_items.insert("Firefox".to_string(), history_item(1, 2));
+        history_items.insert("Slack".to_string(), history_item(5, 3));
+
+        let criteria = PruneCriteria {
+            max_entries: Some(2),
+            older_than: Some(Duration::from_secs(30 * DAY)),
+            listed: Some(HashSet::from([
+                "Insync".to_string(),
+                "Spotify".to_string(),
+                "Firefox".to_string(),
+            ])),
+        };
+
+        let removed = prune(&mut history_items, &criteria, NOW, HALF_LIFE);
+
+        assert_eq!(removed, 2);
+        assert_eq!(
+            history_items.keys().collect::<HashSet<_>>(),
+            HashSet::from([&"Spotify".to_string(), &"Firefox".to_string()])
+        );
+    }
+
+    #[test]
+    fn remove_removes_only_existing_entries() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("history");
+        std::fs::write(&path, "2:Spotify\n1:Insync\n")?;
+
+        remove(&path, "Spotify", NOW)?;
+        assert!(remove(&path, "Firefox", NOW).is_err());
+
+        let (history_items, _) = parse_history_file(&path, NOW)?;
+        assert_eq!(history_items.keys().collect::<Vec<_>>(), ["Insync"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sort_sorts_case_insensitive() -> Result<()> {
+        let history_items = HistoryItems::new();
+        let mut items = vec_of_strings!["insync", "Firefox", "Spotify"];
+        let sorted_items = vec_of_strings!["Firefox", "insync", "Spotify"];
+
+        sort(&history_items, &mut items, NOW, HALF_LIFE);
+
+        assert_eq!(items, sorted_items);
+
+        Ok(())
+    }
+}
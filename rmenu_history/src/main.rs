@@ -1,164 +1,132 @@
 mod cli;
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{Context, Result};
 use clap::Parser;
 use cli::{Cli, Commands};
+use rmenu_history::{
+    modify_history_file, now, parse_history_file, prune, ranked, remove, sort, update,
+    HistoryWarning, PruneCriteria,
+};
 use std::{
-    collections::{hash_map::RandomState, HashMap},
-    fmt::Write,
+    collections::HashSet,
     io::{self, prelude::*},
-    path::PathBuf,
+    path::Path,
+    time::{Duration, UNIX_EPOCH},
 };
 
-type HistoryItems = HashMap<String, i32, RandomState>;
+fn read_items(path: &Path) -> Result<HashSet<String>> {
+    if path == Path::new("-") {
+        return io::stdin()
+            .lock()
+            .lines()
+            .collect::<Result<HashSet<String>, _>>()
+            .context("failed to read items from stdin.");
+    }
 
-fn parse_history_file(path: &PathBuf) -> Result<HistoryItems> {
     let content = std::fs::read_to_string(path)
         .with_context(|| format!("could not read file `{}`.", path.to_string_lossy()))?;
 
-    let history_items_result: Result<HistoryItems> = content
-        .lines()
-        .map(|line| {
-            let split_line: Vec<&str> = line.splitn(2, ':').collect();
-
-            match split_line.as_slice() {
-                [n, a] => match n.parse::<i32>() {
-                    Ok(parsed_n) => Ok(((*a).to_string(), parsed_n)),
-                    Err(_) => Err(anyhow!("could not parse `{}` as an integer.", n)),
-                },
-                _ => Err(anyhow!("could not split `{}`.", line)),
-            }
-        })
-        .collect();
-
-    history_items_result.context("could not parse history file.")
-}
-
-fn write_history_file(path: &PathBuf, history_items: &HistoryItems) -> Result<()> {
-    let mut content = String::new();
-
-    let mut sorted_items = history_items
-        .iter()
-        .map(|(a, n)| (*n, a))
-        .collect::<Vec<(i32, &String)>>();
-
-    sorted_items.sort_by_key(|(n, _)| *n);
-
-    for (n, a) in sorted_items {
-        writeln!(&mut content, "{n}:{a}")
-            .with_context(|| format!("could not format values `{n}`, `{a}`."))?;
-    }
-
-    std::fs::write(path, content)
-        .with_context(|| format!("failed to write content to `{}`.", path.to_string_lossy()))
+    Ok(content.lines().map(ToString::to_string).collect())
 }
 
-fn sort(history_items: &HistoryItems, items: &mut Vec<String>) -> Result<()> {
-    if items.is_empty() {
-        *items = io::stdin()
-            .lock()
-            .lines()
-            .collect::<Result<Vec<String>, _>>()
-            .context("failed to read items from stdin.")?;
+/// Reports the problems found in the history file on stderr
+fn report_warnings(warnings: Vec<HistoryWarning>) {
+    for warning in warnings {
+        eprintln!("{warning}");
     }
-
-    items.sort_by(|a, b| {
-        let x = match history_items.get(a) {
-            Some(n) => (-*n, a.to_ascii_lowercase()),
-            None => (0, a.to_ascii_lowercase()),
-        };
-
-        let y = match history_items.get(b) {
-            Some(n) => (-*n, b.to_ascii_lowercase()),
-            None => (0, b.to_ascii_lowercase()),
-        };
-
-        x.cmp(&y)
-    });
-
-    Ok(())
 }
 
-fn update(path: &PathBuf, entry: String) -> Result<()> {
-    let mut history_items = parse_history_file(path)?;
-
-    let n = match history_items.get(&entry) {
-        Some(n) => *n + 1,
-        None => 1,
-    };
-    history_items.insert(entry, n);
-
-    write_history_file(path, &history_items)?;
-
-    Ok(())
+fn format_time(secs: u64) -> String {
+    humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_secs(secs)).to_string()
 }
 
 fn main() -> Result<()> {
     let args: Cli = Cli::parse();
+    let now = now()?;
 
     match args.cmd {
         Commands::Sort { mut items } => {
-            let history_items = parse_history_file(&args.path)?;
+            if items.is_empty() {
+                items = io::stdin()
+                    .lock()
+                    .lines()
+                    .collect::<Result<Vec<String>, _>>()
+                    .context("failed to read items from stdin.")?;
+            }
 
-            sort(&history_items, &mut items)?;
+            let (history_items, warnings) = parse_history_file(&args.path, now)?;
+            report_warnings(warnings);
+
+            sort(&history_items, &mut items, now, args.half_life);
 
             // Output the sorted list
             println!("{}", items.join("\n"));
 
             Ok(())
         }
-        Commands::Update { entry } => update(&args.path, entry),
-    }
-}
+        Commands::Update { entry } => {
+            let warnings = update(&args.path, entry, now, args.half_life)?;
+            report_warnings(warnings);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    macro_rules! vec_of_strings {
-        ($($x:expr),*) => (vec![$($x.to_string()),*]);
-    }
-
-    #[test]
-    fn sort_sorts_alphabetically() -> Result<()> {
-        let history_items = HistoryItems::new();
-        let mut items = vec_of_strings!["Insync", "Firefox", "Spotify"];
-        let sorted_items = vec_of_strings!["Firefox", "Insync", "Spotify"];
-
-        sort(&history_items, &mut items)?;
-
-        assert_eq!(items, sorted_items);
-
-        Ok(())
-    }
-
-    #[test]
-    fn sort_sorts_historical_items_first() -> Result<()> {
-        let mut history_items = HistoryItems::new();
-
-        history_items.insert("Insync".to_string(), 1);
-        history_items.insert("Spotify".to_string(), 2);
-
-        let mut items = vec_of_strings!["Insync", "Firefox", "Spotify"];
-        let sorted_items = vec_of_strings!["Spotify", "Insync", "Firefox"];
-
-        sort(&history_items, &mut items)?;
-
-        assert_eq!(items, sorted_items);
+            Ok(())
+        }
+        Commands::Remove { entry } => {
+            let warnings = remove(&args.path, &entry, now)?;
+            report_warnings(warnings);
 
-        Ok(())
-    }
+            Ok(())
+        }
+        Commands::Prune {
+            max_entries,
+            older_than,
+            missing_from,
+        } => {
+            let criteria = PruneCriteria {
+                max_entries,
+                older_than,
+                listed: missing_from.as_deref().map(read_items).transpose()?,
+            };
+
+            let (removed, warnings) = modify_history_file(&args.path, now, |history_items| {
+                Ok(prune(history_items, &criteria, now, args.half_life))
+            })?;
+            report_warnings(warnings);
+
+            println!("Removed {removed} entries.");
 
-    #[test]
-    fn sort_sorts_case_insensitive() -> Result<()> {
-        let history_items = HistoryItems::new();
-        let mut items = vec_of_strings!["insync", "Firefox", "Spotify"];
-        let sorted_items = vec_of_strings!["Firefox", "insync", "Spotify"];
+            Ok(())
+        }
+        Commands::List => {
+            let (history_items, warnings) = parse_history_file(&args.path, now)?;
+            report_warnings(warnings);
 
-        sort(&history_items, &mut items)?;
+            for (a, item, frecency) in ranked(&history_items, now, args.half_life) {
+                let last_used = format_time(item.last_used);
+                println!("{frecency:.3}\t{}\t{last_used}\t{a}", item.count);
+            }
 
-        assert_eq!(items, sorted_items);
+            Ok(())
+        }
+        Commands::Stats { top } => {
+            let (history_items, warnings) = parse_history_file(&args.path, now)?;
+            report_warnings(warnings);
+
+            let selections: i64 = history_items
+                .values()
+                .map(|item| i64::from(item.count))
+                .sum();
+            println!("Entries: {}", history_items.len());
+            println!("Selections: {selections}");
+
+            let mut most_selected = history_items.iter().collect::<Vec<_>>();
+            most_selected.sort_by(|(a, x), (b, y)| y.count.cmp(&x.count).then_with(|| a.cmp(b)));
+
+            println!("Most selected:");
+            for (a, item) in most_selected.into_iter().take(top) {
+                println!("{:>8}  {a}", item.count);
+            }
 
-        Ok(())
+            Ok(())
+        }
     }
 }